@@ -0,0 +1,225 @@
+use crate::checksum::fnv1a;
+use crate::input_store::InputStore;
+use crate::solution::{Answer, SolveError};
+use crate::solutions::Day;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io::ErrorKind;
+
+/// Where `aoc verify` looks for known answers
+pub const ANSWERS_FILE: &str = "answers.txt";
+
+/// Identifies a puzzle input regardless of trailing whitespace
+pub fn input_hash(input: &str) -> u64 {
+    fnv1a(input.trim_end().as_bytes())
+}
+
+/// One-line form of an answer, images become their rows joined by `/`
+pub fn encode(answer: &Answer) -> String {
+    match answer {
+        Answer::Number(number) => number.to_string(),
+        Answer::Text(text) => text.clone(),
+        Answer::Image(rows) => rows
+            .iter()
+            .map(|row| row.iter().map(|lit| if *lit { '#' } else { '.' }).collect::<String>())
+            .collect::<Vec<String>>()
+            .join("/"),
+    }
+}
+
+/// Expected answers keyed by day, part and input hash. The file has one
+/// `day part hash answer` line per entry, `#` starts a comment.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Answers {
+    expected: BTreeMap<(u32, u32, u64), String>,
+}
+
+impl Answers {
+    pub fn parse(text: &str) -> Result<Answers, String> {
+        let mut answers = Answers::default();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let fields: Vec<&str> = line.splitn(4, ' ').collect();
+            let entry = match fields.as_slice() {
+                [day, part, hash, answer] => match (day.parse(), part.parse(), u64::from_str_radix(hash, 16)) {
+                    (Ok(day), Ok(part), Ok(hash)) => Some((day, part, hash, answer.trim())),
+                    _ => None,
+                },
+                _ => None,
+            };
+            match entry {
+                Some((day, part, hash, answer)) => {
+                    answers.expected.insert((day, part, hash), answer.to_owned());
+                }
+                None => return Err(format!("line {}: expected `day part hash answer`", number + 1)),
+            }
+        }
+        Ok(answers)
+    }
+
+    /// A missing file is an empty registry, any other read error is reported
+    pub fn load(path: &str) -> Result<Answers, String> {
+        match fs::read_to_string(path) {
+            Ok(text) => Answers::parse(&text).map_err(|message| format!("{}: {}", path, message)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(Answers::default()),
+            Err(e) => Err(format!("Failed reading {}: {}", path, e)),
+        }
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        fs::write(path, self.to_string()).map_err(|e| format!("Failed writing {}: {}", path, e))
+    }
+
+    pub fn get(&self, day: u32, part: u32, input: &str) -> Option<&str> {
+        self.expected.get(&(day, part, input_hash(input))).map(|answer| answer.as_str())
+    }
+
+    pub fn insert(&mut self, day: u32, part: u32, input: &str, answer: &Answer) {
+        self.expected.insert((day, part, input_hash(input)), encode(answer));
+    }
+
+    pub fn len(&self) -> usize {
+        self.expected.len()
+    }
+}
+
+impl fmt::Display for Answers {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "# day part input-hash answer")?;
+        for ((day, part, hash), answer) in &self.expected {
+            writeln!(f, "{} {} {:016x} {}", day, part, hash, answer)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verdict {
+    Pass,
+    Fail { expected: String, actual: String },
+    /// Solved, but nothing to compare against for this input
+    Missing(Answer),
+    Unsolved,
+    Error(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Check {
+    pub day: u32,
+    pub part: u32,
+    pub verdict: Verdict,
+}
+
+impl Check {
+    pub fn failed(&self) -> bool {
+        matches!(self.verdict, Verdict::Fail { .. } | Verdict::Error(_))
+    }
+}
+
+/// Runs both parts of every day on its input from `store` and compares them with `answers`
+pub fn verify(days: &[Day], store: &dyn InputStore, answers: &Answers) -> Vec<Check> {
+    let mut checks = vec![];
+    for day in days {
+        let input = store.input(day.day);
+        for part in 1..=2 {
+            let verdict = match &input {
+                Ok(input) => match (day.solve(part, input), answers.get(day.day, part, input)) {
+                    (Ok(answer), Some(expected)) if encode(&answer) == expected => Verdict::Pass,
+                    (Ok(answer), Some(expected)) => Verdict::Fail {
+                        expected: expected.to_owned(),
+                        actual: encode(&answer),
+                    },
+                    (Ok(answer), None) => Verdict::Missing(answer),
+                    (Err(SolveError::Unsolved), _) => Verdict::Unsolved,
+                    (Err(error), _) => Verdict::Error(error.to_string()),
+                },
+                Err(error) => Verdict::Error(error.to_string()),
+            };
+            checks.push(Check { day: day.day, part, verdict });
+        }
+    }
+    checks
+}
+
+/// Pass/fail/missing table for `aoc verify`
+pub fn table(checks: &[Check]) -> String {
+    let mut table = format!("{:>3} {:>4}  {:<8} {}\n", "day", "part", "result", "details");
+    for check in checks {
+        let (result, details) = match &check.verdict {
+            Verdict::Pass => ("pass", String::new()),
+            Verdict::Fail { expected, actual } => ("FAIL", format!("expected {}, got {}", expected, actual)),
+            Verdict::Missing(answer) => ("missing", encode(answer)),
+            Verdict::Unsolved => ("unsolved", String::new()),
+            Verdict::Error(message) => ("ERROR", message.clone()),
+        };
+        table.push_str(format!("{:>3} {:>4}  {:<8} {}", check.day, check.part, result, details).trim_end());
+        table.push('\n');
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::puzzle_input::InputSource;
+    use crate::solution::Solution;
+
+    struct Echo;
+
+    impl Solution for Echo {
+        fn part1(&self, input: &str) -> Result<Answer, SolveError> {
+            Ok(Answer::Text(input.trim().to_owned()))
+        }
+
+        fn part2(&self, _input: &str) -> Result<Answer, SolveError> {
+            Err(SolveError::Unsolved)
+        }
+    }
+
+    #[test]
+    fn file_round_trip() {
+        let mut answers = Answers::default();
+        answers.insert(8, 2, "0122\n", &Answer::Image(vec![vec![true, false], vec![false, true]]));
+        answers.insert(1, 1, "12", &Answer::Number(2));
+        let text = answers.to_string();
+        assert!(text.ends_with(&format!("1 1 {:016x} 2\n8 2 {:016x} #./.#\n", input_hash("12"), input_hash("0122"))));
+        let parsed = Answers::parse(&text).unwrap();
+        assert_eq!(parsed, answers);
+        assert_eq!(parsed.get(8, 2, "0122"), Some("#./.#"));
+        assert_eq!(parsed.get(8, 2, "0123"), None);
+        assert!(Answers::parse("1 1 nothex 5").is_err());
+    }
+
+    #[test]
+    fn verify_reports_each_part() {
+        let day = Day { day: 4, solution: &Echo };
+        let missing = Day { day: 99, solution: &Echo };
+        let mut answers = Answers::default();
+        answers.insert(4, 1, "152085-670283", &Answer::Text("152085-670283".to_owned()));
+        let checks = verify(&[day, missing], &InputSource::Embedded, &answers);
+        let verdicts: Vec<&Verdict> = checks.iter().map(|check| &check.verdict).collect();
+        assert_eq!(verdicts[0], &Verdict::Pass);
+        assert_eq!(verdicts[1], &Verdict::Unsolved);
+        assert!(checks[2].failed());
+
+        let source = InputSource::Memory("152085-670283".to_owned());
+        let checks = verify(&[day], &source, &Answers::default());
+        assert_eq!(checks[0].verdict, Verdict::Missing(Answer::Text("152085-670283".to_owned())));
+        assert!(table(&checks).contains("  4    1  missing  152085-670283\n"));
+    }
+
+    #[test]
+    fn answers_file_parses() {
+        let answers = Answers::load(ANSWERS_FILE).unwrap();
+        assert_eq!(answers.len(), 31);
+        let input = crate::puzzle_input::embedded(17).unwrap();
+        assert_eq!(answers.get(17, 2, input), Some("1356191"));
+        assert_eq!(Answers::load("nowhere/answers.txt"), Ok(Answers::default()));
+        // a directory can't be read as a file
+        assert!(Answers::load("src").is_err());
+    }
+}
@@ -0,0 +1,318 @@
+use crate::answers::{self, Answers, Verdict};
+use crate::input_store::{self, InputStore};
+use crate::intcode_ascii;
+use crate::intcode_cfg;
+use crate::intcode_debugger;
+use crate::intcode_tracer;
+use crate::puzzle_input::{file_name, InputSource};
+use crate::solution::{Answer, SolveError};
+use crate::solutions::{self, Day};
+use std::error::Error;
+use std::panic::{self, AssertUnwindSafe};
+use std::time::Instant;
+
+const USAGE: &str = "usage: aoc run <day> <part> [--input PATH]
+       aoc run --all
+       aoc verify [--answers PATH] [--record]
+       aoc list
+       aoc debug <program>
+       aoc trace replay <program> <trace>
+       aoc play <program>
+       aoc cfg <program>
+
+Inputs come from $AOC_INPUT_DIR when it is set, the copies built into aoc otherwise.
+With $AOC_SESSION set, missing inputs are downloaded over HTTPS into that directory, or
+input/downloads, when aoc is built with the download feature.";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    List,
+    Run { day: u32, part: u32, input: Option<String> },
+    RunAll,
+    /// `record` adds the answers of parts that had none to the file
+    Verify { answers: String, record: bool },
+    /// Steps through an Intcode program file in the debugger
+    Debug { program: String },
+    /// Checks a JSON-lines trace against a fresh run of the program
+    TraceReplay { program: String, trace: String },
+    /// Talks to an ASCII Intcode program on the terminal
+    Play { program: String },
+    /// Prints the program's control-flow graph as DOT
+    Cfg { program: String },
+}
+
+pub fn parse(args: &[String]) -> Result<Command, String> {
+    let args: Vec<&str> = args.iter().map(|arg| arg.as_str()).collect();
+    match args.as_slice() {
+        ["list"] => Ok(Command::List),
+        ["debug", program] => Ok(Command::Debug { program: program.to_string() }),
+        ["trace", "replay", program, trace] => Ok(Command::TraceReplay {
+            program: program.to_string(),
+            trace: trace.to_string(),
+        }),
+        ["play", program] => Ok(Command::Play { program: program.to_string() }),
+        ["cfg", program] => Ok(Command::Cfg { program: program.to_string() }),
+        ["run", "--all"] => Ok(Command::RunAll),
+        ["run", day, part, rest @ ..] => {
+            let day = day.parse().map_err(|_| format!("invalid day {:?}", day))?;
+            let part = match *part {
+                "1" => 1,
+                "2" => 2,
+                _ => return Err(format!("invalid part {:?}", part)),
+            };
+            let input = match rest {
+                [] => None,
+                ["--input", path] => Some(path.to_string()),
+                _ => return Err(format!("unexpected arguments {:?}", rest)),
+            };
+            Ok(Command::Run { day, part, input })
+        }
+        ["verify", rest @ ..] => {
+            let mut answers = answers::ANSWERS_FILE.to_owned();
+            let mut record = false;
+            let mut rest = rest.iter();
+            while let Some(arg) = rest.next() {
+                match *arg {
+                    "--answers" => answers = rest.next().ok_or("--answers needs a path")?.to_string(),
+                    "--record" => record = true,
+                    _ => return Err(format!("unexpected argument {:?}", arg)),
+                }
+            }
+            Ok(Command::Verify { answers, record })
+        }
+        _ => Err("unknown command".to_owned()),
+    }
+}
+
+fn verify(path: &str, record: bool) -> i32 {
+    let mut answers = match Answers::load(path) {
+        Ok(answers) => answers,
+        Err(message) => {
+            eprintln!("{}", message);
+            return 1;
+        }
+    };
+    let store = match input_store::from_env() {
+        Ok(store) => store,
+        Err(message) => {
+            eprintln!("{}", message);
+            return 1;
+        }
+    };
+    let checks = answers::verify(&solutions::days(), store.as_ref(), &answers);
+    print!("{}", answers::table(&checks));
+    if record {
+        for check in &checks {
+            if let (Verdict::Missing(answer), Ok(input)) = (&check.verdict, store.input(check.day)) {
+                answers.insert(check.day, check.part, &input, answer);
+            }
+        }
+        if let Err(message) = answers.save(path) {
+            eprintln!("{}", message);
+            return 1;
+        }
+    }
+    if checks.iter().any(|check| check.failed()) {
+        1
+    } else {
+        0
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Outcome {
+    Solved,
+    Unsolved,
+    Failed,
+}
+
+/// Solves one part and reports the answer with how long it took
+fn run_part(day: &Day, part: u32, input: &str) -> Outcome {
+    let start = Instant::now();
+    let result = panic::catch_unwind(AssertUnwindSafe(|| day.solve(part, input)));
+    let elapsed = start.elapsed();
+    match result {
+        Ok(Ok(Answer::Image(rows))) => {
+            println!("day {} part {} ({:?}):\n{}", day.day, part, elapsed, Answer::Image(rows));
+            Outcome::Solved
+        }
+        Ok(Ok(answer)) => {
+            println!("day {} part {}: {} ({:?})", day.day, part, answer, elapsed);
+            Outcome::Solved
+        }
+        Ok(Err(SolveError::Unsolved)) => {
+            println!("day {} part {} isn't solved", day.day, part);
+            Outcome::Unsolved
+        }
+        Ok(Err(error)) => {
+            eprintln!("day {} part {} failed after {:?}: {}", day.day, part, elapsed, error);
+            Outcome::Failed
+        }
+        Err(_) => {
+            eprintln!("day {} part {} panicked after {:?}", day.day, part, elapsed);
+            Outcome::Failed
+        }
+    }
+}
+
+/// Exit code of an interactive tool, reporting what stopped it
+fn tool_exit_code(result: Result<(), Box<dyn Error>>) -> i32 {
+    match result {
+        Ok(()) => 0,
+        Err(error) => {
+            eprintln!("{}", error);
+            1
+        }
+    }
+}
+
+/// Runs the command line and returns the exit code
+pub fn main(args: &[String]) -> i32 {
+    let command = match parse(args) {
+        Ok(command) => command,
+        Err(message) => {
+            eprintln!("{}\n{}", message, USAGE);
+            return 2;
+        }
+    };
+    match command {
+        Command::List => {
+            for day in solutions::days() {
+                println!("day {:>2} {}", day.day, file_name(day.day).unwrap_or_default());
+            }
+            0
+        }
+        Command::Run { day, part, input } => {
+            let day = match solutions::find(day) {
+                Some(day) => day,
+                None => {
+                    eprintln!("day {} isn't solved", day);
+                    return 1;
+                }
+            };
+            let store: Box<dyn InputStore> = match input {
+                Some(path) => Box::new(InputSource::Path(path)),
+                None => match input_store::from_env() {
+                    Ok(store) => store,
+                    Err(message) => {
+                        eprintln!("{}", message);
+                        return 1;
+                    }
+                },
+            };
+            let text = match store.input(day.day) {
+                Ok(text) => text,
+                Err(error) => {
+                    eprintln!("{}", error);
+                    return 1;
+                }
+            };
+            match run_part(&day, part, &text) {
+                Outcome::Solved => 0,
+                Outcome::Unsolved | Outcome::Failed => 1,
+            }
+        }
+        Command::RunAll => {
+            let store = match input_store::from_env() {
+                Ok(store) => store,
+                Err(message) => {
+                    eprintln!("{}", message);
+                    return 1;
+                }
+            };
+            let mut failed = vec![];
+            for day in solutions::days() {
+                match store.input(day.day) {
+                    Ok(text) => {
+                        for part in 1..=2 {
+                            if run_part(&day, part, &text) == Outcome::Failed {
+                                failed.push((day.day, part));
+                            }
+                        }
+                    }
+                    Err(error) => {
+                        eprintln!("day {}: {}", day.day, error);
+                        failed.extend(vec![(day.day, 1), (day.day, 2)]);
+                    }
+                }
+            }
+            for (day, part) in &failed {
+                eprintln!("failed: day {} part {}", day, part);
+            }
+            if failed.is_empty() {
+                0
+            } else {
+                1
+            }
+        }
+        Command::Verify { answers, record } => verify(&answers, record),
+        Command::Debug { program } => tool_exit_code(intcode_debugger::debug_file(&program)),
+        Command::TraceReplay { program, trace } => tool_exit_code(intcode_tracer::replay_file(&program, &trace)),
+        Command::Play { program } => tool_exit_code(intcode_ascii::play_file(&program)),
+        Command::Cfg { program } => match intcode_cfg::dot_file(&program) {
+            Ok(dot) => {
+                print!("{}", dot);
+                0
+            }
+            Err(error) => {
+                eprintln!("{}", error);
+                1
+            }
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(|arg| arg.to_owned()).collect()
+    }
+
+    #[test]
+    fn parses_commands() {
+        assert_eq!(parse(&args("list")), Ok(Command::List));
+        assert_eq!(parse(&args("run --all")), Ok(Command::RunAll));
+        assert_eq!(parse(&args("run 7 2")), Ok(Command::Run { day: 7, part: 2, input: None }));
+        assert_eq!(
+            parse(&args("run 9 1 --input other.txt")),
+            Ok(Command::Run { day: 9, part: 1, input: Some("other.txt".to_owned()) })
+        );
+        assert!(parse(&args("run seven 1")).is_err());
+        assert!(parse(&args("run 7 3")).is_err());
+        assert!(parse(&args("run 7 1 --verbose")).is_err());
+        assert_eq!(
+            parse(&args("verify --record")),
+            Ok(Command::Verify { answers: "answers.txt".to_owned(), record: true })
+        );
+        assert_eq!(
+            parse(&args("verify --answers other.txt")),
+            Ok(Command::Verify { answers: "other.txt".to_owned(), record: false })
+        );
+        assert!(parse(&args("verify --answers")).is_err());
+        assert_eq!(parse(&args("debug day_nine.txt")), Ok(Command::Debug { program: "day_nine.txt".to_owned() }));
+        assert!(parse(&args("debug")).is_err());
+        assert_eq!(
+            parse(&args("trace replay day_nine.txt run.jsonl")),
+            Ok(Command::TraceReplay { program: "day_nine.txt".to_owned(), trace: "run.jsonl".to_owned() })
+        );
+        assert!(parse(&args("trace replay day_nine.txt")).is_err());
+        assert_eq!(parse(&args("play day_seventeen.txt")), Ok(Command::Play { program: "day_seventeen.txt".to_owned() }));
+        assert_eq!(parse(&args("cfg day_nine.txt")), Ok(Command::Cfg { program: "day_nine.txt".to_owned() }));
+        assert!(parse(&[]).is_err());
+    }
+
+    #[test]
+    fn registry_covers_solved_days() {
+        assert_eq!(solutions::days().len(), 16);
+        assert!(solutions::find(10).is_none());
+        assert_eq!(main(&args("run 12 2")), 1);
+        assert_eq!(main(&args("run 4 1")), 0);
+        assert_eq!(main(&args("run 4 1 --input missing.txt")), 1);
+        assert_eq!(main(&args("debug missing.txt")), 1);
+        assert_eq!(main(&args("trace replay input/day_nine.txt missing.jsonl")), 1);
+        assert_eq!(main(&args("play missing.txt")), 1);
+        assert_eq!(main(&args("cfg missing.txt")), 1);
+    }
+}
@@ -1,10 +1,5 @@
-use std::fs;
 use itertools::Itertools;
-
-fn get_input() -> String {
-    fs::read_to_string("input/day_eight.txt")
-        .expect("Something went wrong reading the file")
-}
+use crate::solution::{Answer, Solution, SolveError};
 
 const WIDTH: i32 = 25;
 const HEIGHT: i32 = 6;
@@ -14,57 +9,59 @@ const WHITE: u32 = 1;
 const TRANSPARENT: u32 = 2;
 
 
-pub fn one() {
-    let input_text = get_input();
-    let layers = input_text
-        .chars()
-        .filter_map(|letter| letter.to_digit(10))
-        .chunks((WIDTH * HEIGHT) as usize);
-    let mut counter = vec![];
-    for layer in &layers {
-        let layer = layer.collect::<Vec<u32>>();
-        let zero_count = layer.iter().filter(|digit| **digit == 0).count();
-        let one_count = layer.iter().filter(|digit| **digit == 1).count();
-        let two_count = layer.iter().filter(|digit| **digit == 2).count();
-
-        counter.push((zero_count, one_count * two_count));
-    }
-
-    let min = counter.iter().min_by_key(|count| count.0);
-    println!("{:?}", min);
-}
+pub struct DayEight;
 
-pub fn two() {
-    let input_text = get_input();
-    let layer_data = input_text
-        .chars()
-        .filter_map(|letter| letter.to_digit(10))
-        .chunks((WIDTH * HEIGHT) as usize);
-    let mut layers = vec![];
-    for layer in &layer_data {
-        let layer = layer.collect::<Vec<u32>>();
-        layers.push(Layer::new(layer))
-    }
+impl Solution for DayEight {
+    fn part1(&self, input: &str) -> Result<Answer, SolveError> {
+        let layers = input
+            .chars()
+            .filter_map(|letter| letter.to_digit(10))
+            .chunks((WIDTH * HEIGHT) as usize);
+        let mut counter = vec![];
+        for layer in &layers {
+            let layer = layer.collect::<Vec<u32>>();
+            let zero_count = layer.iter().filter(|digit| **digit == 0).count();
+            let one_count = layer.iter().filter(|digit| **digit == 1).count();
+            let two_count = layer.iter().filter(|digit| **digit == 2).count();
 
-    // first layer is in the front
-    layers.reverse();
+            counter.push((zero_count, one_count * two_count));
+        }
 
-    let mut last_layer = layers.first().unwrap().clone();
-    for layer in layers.iter().skip(1) {
-        last_layer.apply_layer(layer);
+        let min = counter
+            .iter()
+            .min_by_key(|count| count.0)
+            .ok_or_else(|| SolveError::Parse("image has no layers".to_owned()))?;
+        Ok(Answer::Number(min.1 as i64))
     }
 
-    let display_char = if rand::random() {
-        "🌈"
-    } else {
-        "❤️"
-    };
+    fn part2(&self, input: &str) -> Result<Answer, SolveError> {
+        let layer_data = input
+            .chars()
+            .filter_map(|letter| letter.to_digit(10))
+            .chunks((WIDTH * HEIGHT) as usize);
+        let mut layers = vec![];
+        for layer in &layer_data {
+            let layer = layer.collect::<Vec<u32>>();
+            layers.push(Layer::new(layer))
+        }
+
+        // first layer is in the front
+        layers.reverse();
 
-    for line in last_layer.image_data.chunks(WIDTH as usize) {
-        for pixel in line {
-            print!("{}", if *pixel == 1 {display_char} else {" "});
+        let mut last_layer = layers
+            .first()
+            .ok_or_else(|| SolveError::Parse("image has no layers".to_owned()))?
+            .clone();
+        for layer in layers.iter().skip(1) {
+            last_layer.apply_layer(layer);
         }
-        println!("");
+
+        let rows = last_layer
+            .image_data
+            .chunks(WIDTH as usize)
+            .map(|line| line.iter().map(|pixel| *pixel == WHITE).collect())
+            .collect();
+        Ok(Answer::Image(rows))
     }
 }
 
@@ -86,3 +83,22 @@ impl Layer {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::puzzle_input::embedded;
+
+    #[test]
+    fn day_8_answers() {
+        let input = embedded(8).unwrap();
+        assert_eq!(DayEight.part1(input).unwrap(), Answer::Number(1572));
+        match DayEight.part2(input).unwrap() {
+            Answer::Image(rows) => {
+                assert_eq!(rows.len(), HEIGHT as usize);
+                assert!(rows.iter().all(|row| row.len() == WIDTH as usize));
+            }
+            answer => panic!("expected an image, got {:?}", answer),
+        }
+    }
+}
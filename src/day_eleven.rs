@@ -1,4 +1,5 @@
-use crate::infinite_memory_intcomputer::{IntcodeComputer, IntcodeComputerState};
+use crate::infinite_memory_intcomputer::{parse_program, IntcodeComputer, IntcodeComputerState};
+use crate::solution::{Answer, Solution, SolveError};
 use std::collections::HashMap;
 use std::error::*;
 
@@ -24,9 +25,9 @@ struct Robot {
 }
 
 impl Robot {
-    fn new() -> Robot {
+    fn new(input: &str) -> Robot {
         Robot {
-            computer: IntcodeComputer::new_from_file("input/day_eleven.txt"),
+            computer: IntcodeComputer::new(parse_program(input)),
             map: HashMap::new(),
             direction: Direction::Up,
             position: (0, 0),
@@ -81,49 +82,45 @@ impl Robot {
     }
 }
 
-pub fn one() {
-    let mut robot = Robot::new();
-    robot.run().unwrap();
-    println!("{:?}", robot.painted_panels);
-}
-
-pub fn two() {
-    let mut robot = Robot::new();
-    robot.map.insert((0, 0), WHITE);
-    robot.run().unwrap();
-    let mut picture = robot.map.clone();
-    picture.insert((0, 0), 3);
+pub struct DayEleven;
 
-    let mut top_left = (std::i32::MAX, std::i32::MAX);
-    let mut bottom_right = (std::i32::MIN, std::i32::MIN);
-    for point in picture.keys() {
-        if point.0 < top_left.0 {
-            top_left.0 = point.0
-        }
-        if point.1 < top_left.1 {
-            top_left.1 = point.1
-        }
-        if point.0 > bottom_right.0 {
-            bottom_right.0 = point.0
-        }
-        if point.1 > bottom_right.1 {
-            bottom_right.1 = point.1
-        }
+impl Solution for DayEleven {
+    fn part1(&self, input: &str) -> Result<Answer, SolveError> {
+        let mut robot = Robot::new(input);
+        robot.run()?;
+        Ok(Answer::Number(robot.painted_panels as i64))
     }
-    println!("tl {:?}", top_left);
-    println!("br {:?}", bottom_right);
-    for x in top_left.0..(bottom_right.0+1) {
-        for y in top_left.1..(bottom_right.1+1) {
-            let pixel = picture.get(&(x, y)).unwrap_or(&BLACK);
-            if pixel == &WHITE {
-                print!("#");
-            } else if pixel == &BLACK {
-                print!(" ");
-            } else {
-                print!("O")
+
+    fn part2(&self, input: &str) -> Result<Answer, SolveError> {
+        let mut robot = Robot::new(input);
+        robot.map.insert((0, 0), WHITE);
+        robot.run()?;
+        let picture = &robot.map;
+
+        let mut top_left = (std::i32::MAX, std::i32::MAX);
+        let mut bottom_right = (std::i32::MIN, std::i32::MIN);
+        for point in picture.keys() {
+            if point.0 < top_left.0 {
+                top_left.0 = point.0
+            }
+            if point.1 < top_left.1 {
+                top_left.1 = point.1
+            }
+            if point.0 > bottom_right.0 {
+                bottom_right.0 = point.0
+            }
+            if point.1 > bottom_right.1 {
+                bottom_right.1 = point.1
             }
         }
-        println!("");
+        let rows = (top_left.1..(bottom_right.1+1))
+            .map(|y| {
+                (top_left.0..(bottom_right.0+1))
+                    .map(|x| picture.get(&(x, y)) == Some(&WHITE))
+                    .collect()
+            })
+            .collect();
+        Ok(Answer::Image(rows))
     }
 }
 
@@ -131,19 +128,27 @@ pub fn two() {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::puzzle_input::embedded;
 
     #[test]
     fn day_11_task_1() {
-        let mut robot = Robot::new();
+        let input = embedded(11).unwrap();
+        let mut robot = Robot::new(input);
         robot.run().unwrap();
         assert_eq!(2469, robot.painted_panels);
     }
 
     #[test]
     fn day_11_task_2() {
-        let mut robot = Robot::new();
+        let input = embedded(11).unwrap();
+        let mut robot = Robot::new(input);
         robot.map.insert((0, 0), WHITE);
         robot.run().unwrap();
         assert_eq!(248, robot.painted_panels);
+        match DayEleven.part2(input).unwrap() {
+            // registration identifiers are eight letters, six pixels high
+            Answer::Image(rows) => assert_eq!(rows.len(), 6),
+            answer => panic!("expected an image, got {:?}", answer),
+        }
     }
 }
\ No newline at end of file
@@ -1,12 +1,41 @@
-use crate::intcode_computer::*;
+use crate::infinite_memory_intcomputer::{parse_program, IntcodeComputer};
+use crate::solution::{Answer, Solution, SolveError};
+use std::error;
 
+fn diagnostic_code(input: &str, system_id: i64) -> Result<i64, Box<dyn error::Error>> {
+    let mut computer = IntcodeComputer::new(parse_program(input));
+    computer.provide_input(system_id);
+    let output = computer.run_until_halt()?;
+    // every output but the last is a test result that has to be 0
+    Ok(*output.last().ok_or("Diagnostic program didn't produce a code")?)
+}
+
+pub struct DayFive;
+
+impl Solution for DayFive {
+    fn part1(&self, input: &str) -> Result<Answer, SolveError> {
+        Ok(Answer::Number(diagnostic_code(input, 1)?))
+    }
 
-pub fn one() {
-    let mut program = load_input("input/day_five.txt");
-    process_intcode(&mut program, None);
+    fn part2(&self, input: &str) -> Result<Answer, SolveError> {
+        Ok(Answer::Number(diagnostic_code(input, 5)?))
+    }
 }
 
-pub fn two() {
-    let mut program = load_input("input/day_five.txt");
-    process_intcode(&mut program, None);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::puzzle_input::embedded;
+
+    #[test]
+    fn air_conditioner_diagnostic() {
+        let input = embedded(5).unwrap();
+        assert_eq!(diagnostic_code(input, 1).unwrap(), 9006673);
+    }
+
+    #[test]
+    fn thermal_radiator_diagnostic() {
+        let input = embedded(5).unwrap();
+        assert_eq!(DayFive.part2(input).unwrap(), Answer::Number(3629692));
+    }
 }
@@ -1,3 +1,5 @@
+use crate::solution::{parse_number, Answer, Solution, SolveError};
+
 fn passes(input: i32) -> bool {
     let input_string = input.to_string();
     if input_string.len() != 6 {
@@ -39,32 +41,38 @@ fn second_task_pass(input: &str) -> bool {
     sequence_counter.iter().any(|(_, count)| *count == 2)
 }
 
-pub fn one(){
-    let input = "152085-670283";
-    let mut inputs = input.split("-").filter_map(|s| s.parse::<i32>().ok());
-    let start = inputs.next().unwrap();
-    let end = inputs.next().unwrap();
-    let mut counter = 0;
-    for password in start..end {
-        if passes(password) {
-            counter+=1;
-        }
+fn parse_range(input: &str) -> Result<(i32, i32), SolveError> {
+    let mut inputs = input.trim().split("-");
+    match (inputs.next(), inputs.next()) {
+        (Some(start), Some(end)) => Ok((parse_number(start)?, parse_number(end)?)),
+        _ => Err(SolveError::Parse(format!("{:?} isn't a range", input))),
     }
-    println!("{}", counter);
 }
 
-pub fn two(){
-    let input = "152085-670283";
-    let mut inputs = input.split("-").filter_map(|s| s.parse::<i32>().ok());
-    let start = inputs.next().unwrap();
-    let end = inputs.next().unwrap();
-    let mut counter = 0;
-    for password in start..end {
-        if passes(password) && second_task_pass(&password.to_string()) {
-            counter+=1;
+pub struct DayFour;
+
+impl Solution for DayFour {
+    fn part1(&self, input: &str) -> Result<Answer, SolveError> {
+        let (start, end) = parse_range(input)?;
+        let mut counter = 0;
+        for password in start..end {
+            if passes(password) {
+                counter+=1;
+            }
         }
+        Ok(Answer::Number(counter))
+    }
+
+    fn part2(&self, input: &str) -> Result<Answer, SolveError> {
+        let (start, end) = parse_range(input)?;
+        let mut counter = 0;
+        for password in start..end {
+            if passes(password) && second_task_pass(&password.to_string()) {
+                counter+=1;
+            }
+        }
+        Ok(Answer::Number(counter))
     }
-    println!("{}", counter);
 }
 
 #[cfg(test)]
@@ -100,4 +108,11 @@ mod tests {
     fn second_task_pass_three() {
         assert!(second_task_pass("111122"));
     }
+
+    #[test]
+    fn day_4_answers() {
+        assert_eq!(DayFour.part1("152085-670283").unwrap(), Answer::Number(1764));
+        assert_eq!(DayFour.part2("152085-670283").unwrap(), Answer::Number(1196));
+        assert!(DayFour.part1("152085").is_err());
+    }
 }
\ No newline at end of file
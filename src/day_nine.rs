@@ -1,32 +1,40 @@
 use crate::infinite_memory_intcomputer::*;
+use crate::intcode_io::QueueIo;
+use crate::solution::{Answer, Solution, SolveError};
 
-pub fn one() {
-    let mut computer = IntcodeComputer::new_from_file("input/day_nine.txt");
-    computer.provide_input(1);
-    loop {
-        match computer.run().unwrap() {
-            IntcodeComputerState::Halted => break,
-            IntcodeComputerState::OutputProduced(output) => println!("Output {}", output),
-            IntcodeComputerState::WaitingForInput => panic!("Unexpected waiting for input"),
-        }
+fn run_boost(input: &str, mode: i64) -> Result<Vec<i64>, IntcodeError> {
+    let mut computer = IntcodeComputer::new(parse_program(input));
+    let mut io = QueueIo::new(vec![mode]);
+    match computer.run_with_io(&mut io)? {
+        IntcodeComputerState::Halted => Ok(io.output.into_iter().collect()),
+        _ => Err(IntcodeError::InputExhausted),
     }
 }
 
-pub fn two() {
-    let mut computer = IntcodeComputer::new_from_file("input/day_nine.txt");
-    computer.provide_input(2);
-    loop {
-        match computer.run().unwrap() {
-            IntcodeComputerState::Halted => break,
-            IntcodeComputerState::OutputProduced(output) => println!("Output {}", output),
-            IntcodeComputerState::WaitingForInput => panic!("Unexpected waiting for input"),
-        }
+/// BOOST prints the opcodes it found broken before its keycode
+fn boost_keycode(input: &str, mode: i64) -> Result<Answer, SolveError> {
+    match run_boost(input, mode)?.as_slice() {
+        [keycode] => Ok(Answer::Number(*keycode)),
+        outputs => Err(SolveError::NoAnswer(format!("BOOST reported failing opcodes {:?}", outputs))),
+    }
+}
+
+pub struct DayNine;
+
+impl Solution for DayNine {
+    fn part1(&self, input: &str) -> Result<Answer, SolveError> {
+        boost_keycode(input, 1)
+    }
+
+    fn part2(&self, input: &str) -> Result<Answer, SolveError> {
+        boost_keycode(input, 2)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::puzzle_input::embedded;
 
     #[test]
     fn test_1() {
@@ -65,22 +73,20 @@ mod tests {
 
     #[test]
     fn int_code_computer_self_test() {
-        let mut computer = IntcodeComputer::new_from_file("input/day_nine.txt");
-        computer.provide_input(1);
-        loop {
-            match computer.run().unwrap() {
-                IntcodeComputerState::Halted => break,
-                IntcodeComputerState::OutputProduced(output) => assert_eq!(output, 3100786347),
-                IntcodeComputerState::WaitingForInput => panic!("Unexpected waiting for input"),
-            }
-        }
+        let input = embedded(9).unwrap();
+        assert_eq!(run_boost(input, 1).unwrap(), vec![3100786347]);
+        assert_eq!(DayNine.part1(input).unwrap(), Answer::Number(3100786347));
     }
 
     #[test]
     fn int_computer_extended_self_test() {
-        let mut computer = IntcodeComputer::new_from_file("input/day_nine.txt");
-        computer.provide_input(2);
-        computer.run_ignore_output().unwrap();
-        assert_eq!(computer.get_output(), vec![87023])
+        let input = embedded(9).unwrap();
+        assert_eq!(run_boost(input, 2).unwrap(), vec![87023])
+    }
+
+    #[test]
+    fn boost_needs_its_mode() {
+        let mut computer = IntcodeComputer::new(parse_program(embedded(9).unwrap()));
+        assert_eq!(computer.run_with_io(QueueIo::default()).unwrap(), IntcodeComputerState::Blocked);
     }
 }
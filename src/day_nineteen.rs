@@ -1,28 +1,24 @@
-use crate::infinite_memory_intcomputer::{IntcodeComputer, IntcodeComputerState::OutputProduced};
+use crate::infinite_memory_intcomputer::{parse_program, IntcodeComputer, IntcodeComputerState::OutputProduced};
+use crate::solution::{Answer, Solution, SolveError};
 use std::collections::{HashMap, HashSet};
 
 const STATIONARY: i64 = 0;
 const MOVING: i64 = 1;
 
-pub fn one() {
+fn affected_points(input: &str) -> Result<i64, SolveError> {
+    let program = parse_program(input);
     let mut map = HashMap::new();
     for y in 0..50 {
         for x in 0..50 {
-            let mut computer = IntcodeComputer::new_from_file("input/day_nineteen.txt");
+            let mut computer = IntcodeComputer::new(program.clone());
             computer.provide_input(x);
             computer.provide_input(y);
-            if let OutputProduced(output) = computer.run().unwrap() {
+            if let OutputProduced(output) = computer.run()? {
                 map.insert((x, y), output);
-                if output == STATIONARY {
-                    print!(".");
-                } else {
-                    print!("#");
-                }
             } else {
-                panic!("Computer didn't return value {} {}", x, y);
+                return Err(SolveError::NoAnswer(format!("drone didn't report {} {}", x, y)));
             }
         }
-        println!();
     }
     let mut affected = 0;
     for (_, effect) in map {
@@ -30,7 +26,7 @@ pub fn one() {
             affected += 1;
         }
     }
-    println!("{}", affected);
+    Ok(affected)
 }
 
 fn check_pos(pos: &(i64, i64), computer: &IntcodeComputer) -> bool {
@@ -55,8 +51,8 @@ fn check_ship(far_left: &(i64, i64), map: &HashSet<(i64, i64)>) -> bool {
     true
 }
 
-pub fn two() {
-    let computer = IntcodeComputer::new_from_file("input/day_nineteen.txt");
+fn closest_ship_position(input: &str) -> i64 {
+    let computer = IntcodeComputer::new(parse_program(input));
     let mut map = HashSet::new();
     let mut y = 20;
     let mut start_x = 0;
@@ -86,29 +82,34 @@ pub fn two() {
         }
         if check_ship(&(current_line[0], y), &map) {
             let closest_x = current_line[0];
-            let farthest_x = current_line[0] + 99;
             let closest_y = y - 99;
-            let farthest_y = y;
-            // debug draw
-            for y in (closest_y - 5)..(closest_y + 110) {
-                for x in (closest_x - 5)..(closest_x + 110) {
-                    if map.contains(&(x, y)) {
-                        if x >= closest_x && x <= farthest_x && y >= closest_y && y <= farthest_y {
-                            print!("O");
-                        } else {
-                            print!("#");
-                        }
-                    } else {
-                        print!(".");
-                    }
-                }
-                println!();
-            }
-
-            println!("closest point coordinates {}", closest_x * 10000 + closest_y);
-            return;
+            return closest_x * 10000 + closest_y;
         }
-        println!("current width {} at line {}", current_line.len(), y);
         y+=1;
     }
 }
+
+pub struct DayNineteen;
+
+impl Solution for DayNineteen {
+    fn part1(&self, input: &str) -> Result<Answer, SolveError> {
+        Ok(Answer::Number(affected_points(input)?))
+    }
+
+    fn part2(&self, input: &str) -> Result<Answer, SolveError> {
+        Ok(Answer::Number(closest_ship_position(input)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::puzzle_input::embedded;
+
+    #[test]
+    fn day_19_answers() {
+        let input = embedded(19).unwrap();
+        assert_eq!(DayNineteen.part1(input).unwrap(), Answer::Number(192));
+        assert_eq!(DayNineteen.part2(input).unwrap(), Answer::Number(8381082));
+    }
+}
@@ -1,21 +1,12 @@
-use std::fs;
 use math::round;
+use crate::solution::{parse_number, Answer, Solution, SolveError};
 
-fn get_input() -> String {
-    fs::read_to_string("input/day_one.txt")
-        .expect("Something went wrong reading the file")
+fn parse_masses(input: &str) -> Result<Vec<f64>, SolveError> {
+    input.lines().map(parse_number).collect()
 }
 
-pub fn one() {
-    let contents = get_input();
-
-    let total_fuel: i32 = contents
-                        .lines()
-                        .map(|s| s.parse::<f64>().unwrap())
-                        .map(|v| (round::floor(v / 3.0, 0) - 2.0) as i32)
-                        .sum();
-
-    println!("Total fuel needed is {}", total_fuel);
+fn calc_fuel(mass: f64) -> i32 {
+    (round::floor(mass / 3.0, 0) - 2.0) as i32
 }
 
 fn calc_fuel_all(mass: f64) -> i32 {
@@ -32,15 +23,35 @@ fn calc_fuel_all(mass: f64) -> i32 {
     fuel_needed
 }
 
-pub fn two() {
-    let contents = get_input();
+pub struct DayOne;
+
+impl Solution for DayOne {
+    fn part1(&self, input: &str) -> Result<Answer, SolveError> {
+        let total_fuel: i32 = parse_masses(input)?.into_iter().map(calc_fuel).sum();
+        Ok(Answer::Number(total_fuel as i64))
+    }
+
+    fn part2(&self, input: &str) -> Result<Answer, SolveError> {
+        let total_fuel: i32 = parse_masses(input)?.into_iter().map(calc_fuel_all).sum();
+        Ok(Answer::Number(total_fuel as i64))
+    }
+}
 
-    let total_fuel: i32 = contents
-                        .lines()
-                        .map(|s| s.parse::<f64>().unwrap())
-                        .map(|v| calc_fuel_all(v))
-                        .sum();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::puzzle_input::embedded;
 
-    println!("Total fuel needed is {}", total_fuel);
+    #[test]
+    fn fuel_examples() {
+        assert_eq!(calc_fuel(1969.0), 654);
+        assert_eq!(calc_fuel_all(100756.0), 50346);
+    }
 
-}
\ No newline at end of file
+    #[test]
+    fn day_1_answers() {
+        let input = embedded(1).unwrap();
+        assert_eq!(DayOne.part1(input).unwrap(), Answer::Number(3337604));
+        assert_eq!(DayOne.part2(input).unwrap(), Answer::Number(5003530));
+    }
+}
@@ -1,93 +1,58 @@
-use crate::intcode_computer::*;
-use crate::infinite_memory_intcomputer::*;
-use itertools::*;
+use crate::infinite_memory_intcomputer::parse_program;
+use crate::intcode_topology::amplifier_chain;
+use crate::solution::{Answer, Solution, SolveError};
 use std::error;
 
-fn run_thruster_computer(
-    phase_setting: i32,
-    input_signal: i32,
-    program: &Vec<i32>,
-) -> Result<i32, Box<dyn error::Error>> {
-    let mut program = program.clone();
-    let inputs = vec![phase_setting, input_signal];
-    let output = process_intcode(&mut program, Some(inputs));
-    let res = output.first().ok_or("Thruster didn't produce a result")?;
-    Ok(*res)
-}
-
 fn run_all_thursters(
-    phase_sequence: Vec<i32>,
-    program: &Vec<i32>,
-) -> Result<i32, Box<dyn error::Error>> {
-    let mut input_signal = 0;
-    for phase_setting in phase_sequence {
-        input_signal = run_thruster_computer(phase_setting, input_signal, program)?;
-    }
-    Ok(input_signal)
-}
-
-pub fn one() {
-    let program = load_input("input/day_seven.txt");
-    let max = (0..5)
-        .permutations(5)
-        .map(|s| run_all_thursters(s, &program))
-        .filter_map(Result::ok)
-        .max();
-    println!("{:?}", max);
+    phase_sequence: Vec<i64>,
+    program: &Vec<i64>,
+) -> Result<i64, Box<dyn error::Error>> {
+    run_amplifiers(phase_sequence, program, false)
 }
 
 fn run_all_thursters_together(
     phase_sequence: Vec<i64>,
     program: Vec<i64>,
 ) -> Result<i64, Box<dyn error::Error>> {
-    let mut computers = vec![];
-    for phase in phase_sequence {
-        let mut computer = IntcodeComputer::new(program.clone());
-        computer.run()?;
-        computer.provide_input(phase);
-        computers.push(computer);
-    }
-    let mut last_output = Some(0);
-    let mut done = false;
-    while !done {
-        for computer in &mut computers {
-            loop {
-                match computer.run()? {
-                    IntcodeComputerState::WaitingForInput => {
-                        computer.provide_input(last_output.unwrap());
-                        last_output = None;
-                    },
-                    IntcodeComputerState::OutputProduced(output) => {
-                        last_output = Some(output);
-                        break;
-                    },
-                    IntcodeComputerState::Halted => {
-                        done = true;
-                        break;
-                    },
-                }
-            }
-            if done {
-                break;
-            }
-        }
+    run_amplifiers(phase_sequence, &program, true)
+}
+
+fn run_amplifiers(phase_sequence: Vec<i64>, program: &[i64], feedback: bool) -> Result<i64, Box<dyn error::Error>> {
+    let (mut topology, amplifiers) = amplifier_chain(program, phase_sequence.len(), feedback);
+    for (amplifier, phase) in amplifiers.iter().zip(phase_sequence) {
+        topology.set_phase(*amplifier, phase)?;
     }
-    Ok(last_output.unwrap())
+    let last = *amplifiers.last().ok_or("No amplifiers")?;
+    Ok(topology.signal(last)?)
 }
 
-pub fn two() {
-    let program: Vec<i64> = load_input("input/day_seven.txt").iter().map(|i| *i as i64).collect();
-    let max = (5..10)
-        .permutations(5)
-        .map(|s| run_all_thursters_together(s, program.clone()))
-        .filter_map(Result::ok)
-        .max();
-    println!("{:?}", max);
+fn max_thruster_signal(program: &[i64], phases: Vec<i64>, feedback: bool) -> Result<i64, Box<dyn error::Error>> {
+    let (topology, amplifiers) = amplifier_chain(program, phases.len(), feedback);
+    let last = *amplifiers.last().ok_or("No amplifiers")?;
+    let (_, signal) = topology
+        .search_phases(&amplifiers, &phases, last)?
+        .ok_or("No phase settings to try")?;
+    Ok(signal)
+}
+
+pub struct DaySeven;
+
+impl Solution for DaySeven {
+    fn part1(&self, input: &str) -> Result<Answer, SolveError> {
+        let program = parse_program(input);
+        Ok(Answer::Number(max_thruster_signal(&program, (0..5).collect(), false)?))
+    }
+
+    fn part2(&self, input: &str) -> Result<Answer, SolveError> {
+        let program = parse_program(input);
+        Ok(Answer::Number(max_thruster_signal(&program, (5..10).collect(), true)?))
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::puzzle_input::embedded;
 
     #[test]
     fn test_1() {
@@ -120,14 +85,8 @@ mod tests {
 
     #[test]
     fn day_7_task_1() {
-        let program = load_input("input/day_seven.txt");
-        let max = (0..5)
-            .permutations(5)
-            .map(|s| run_all_thursters(s, &program))
-            .filter_map(Result::ok)
-            .max()
-            .unwrap();
-        assert_eq!(max, 87138);
+        let program = parse_program(embedded(7).unwrap());
+        assert_eq!(max_thruster_signal(&program, (0..5).collect(), false).unwrap(), 87138);
     }
 
     #[test]
@@ -153,13 +112,14 @@ mod tests {
 
     #[test]
     fn day_7_task_2() {
-        let program: Vec<i64> = load_input("input/day_seven.txt").iter().map(|i| *i as i64).collect();
-        let max = (5..10)
-            .permutations(5)
-            .map(|s| run_all_thursters_together(s, program.clone()))
-            .filter_map(Result::ok)
-            .max()
-            .unwrap();
-        assert_eq!(max, 17279674);
+        let program = parse_program(embedded(7).unwrap());
+        assert_eq!(max_thruster_signal(&program, (5..10).collect(), true).unwrap(), 17279674);
+    }
+
+    #[test]
+    fn day_7_answers() {
+        let input = embedded(7).unwrap();
+        assert_eq!(DaySeven.part1(input).unwrap(), Answer::Number(87138));
+        assert_eq!(DaySeven.part2(input).unwrap(), Answer::Number(17279674));
     }
 }
@@ -1,9 +1,10 @@
-use crate::infinite_memory_intcomputer::{IntcodeComputer, ParamMode};
+use crate::infinite_memory_intcomputer::{parse_program, IntcodeComputer, ParamMode};
+use crate::intcode_ascii::AsciiComputer;
+use crate::solution::{Answer, Solution, SolveError};
 use std::collections::{HashSet, HashMap,};
 
 const SCAFFOLD: i64 = 35;
 const OPEN: i64 = 46;
-const NEW_LINE: i64 = 10;
 
 fn is_scaffolding(input: &char) -> bool {
     "<>^v#".contains(*input)
@@ -17,19 +18,16 @@ fn is_intersection(pos: &(i32, i32), map: &HashSet<(i32, i32)>) -> bool {
     map.contains(&(pos.0, pos.1+1))
 }
 
-pub fn one() {
-    let mut computer = IntcodeComputer::new_from_file("input/day_seventeen.txt");
-    computer.run_ignore_output().unwrap();
-    let data = computer.get_output();
+fn sum_of_alignments(input: &str) -> Result<i32, SolveError> {
+    let computer = IntcodeComputer::new(parse_program(input));
+    let data = AsciiComputer::new(computer).read_until_prompt()?;
     let mut scaffolding_map = HashSet::new();
     let mut map = HashMap::new();
     let mut x = 0;
     let mut y = 0;
     let mut width = 0;
-    for point in data {
-        let pixel = point as u8 as char;
-        print!("{}", pixel);
-        if point == NEW_LINE {
+    for pixel in data.chars() {
+        if pixel == '\n' {
             y+=1;
             if x > width {
                 width = x;
@@ -44,7 +42,6 @@ pub fn one() {
         }
     }
     let height = y - 1;
-    println!("width {} height {}", width, height);
     // find intersections
     let mut intersections = HashSet::new();
     for y in 0..(height+1) {
@@ -55,18 +52,11 @@ pub fn one() {
             }
         }
     }
-    for y in 0..height {
-        for x in 0..width {
-            print!("{}", map.get(&(x, y)).unwrap());
-        }
-        println!("");
-    }
     let mut sum_of_alignments = 0;
     for (x, y) in intersections {
-        println!("inter at <{} {}> mul {}", x, y, x * y);
         sum_of_alignments += x * y;
     }
-    println!("Sum of alignments {}", sum_of_alignments);
+    Ok(sum_of_alignments)
 }
 
 const LEFT: char = '<';
@@ -93,15 +83,6 @@ impl RobotDirection {
         }
     }
 
-    fn to_char(&self) -> char {
-        match self {
-            RobotDirection::Up => UP,
-            RobotDirection::Down => DOWN,
-            RobotDirection::Left => LEFT,
-            RobotDirection::Right => RIGHT,
-        }
-    }
-
     fn move_point(&self, point: &(i32, i32)) -> (i32, i32) {
         let (x, y) = point;
         match self {
@@ -126,8 +107,6 @@ struct ScaffoldingMap {
     points: HashSet<(i32, i32)>,
     robot_start: (i32, i32),
     robot_direction: RobotDirection,
-    width: i32,
-    height: i32,
 }
 
 fn is_robot(input: &char) -> bool {
@@ -139,12 +118,8 @@ impl ScaffoldingMap {
         let mut points = HashSet::new();
         let mut robot_start = (0, 0);
         let mut robot_direction = RobotDirection::Up;
-        let mut width = 0;
-        let mut height = 0;
         for (y, line) in input.lines().enumerate() {
-            height = height.max(y as i32);
             for (x, point) in line.chars().enumerate() {
-                width = width.max(x as i32 + 1);
                 if point as i64 == SCAFFOLD {
                     points.insert((x as i32, y as i32));
                 } else if is_robot(&point) {
@@ -157,44 +132,6 @@ impl ScaffoldingMap {
             points,
             robot_start,
             robot_direction,
-            width,
-            height,
-        }
-    }
-
-    fn render(&self, erase: bool) {
-        if erase {
-            println!("\x1b[{}F", self.height + 1);
-        }
-        for y in 0..self.height {
-            for x in 0..self.width {
-                let point = if self.robot_start == (x, y) {
-                    self.robot_direction.to_char()
-                } else if self.points.contains(&(x, y)) {
-                    '#'
-                } else {
-                    '.'
-                };
-                print!("{}", point);
-            }
-            println!("");
-        }
-    }
-
-    fn render_with_robot(&self, position: &(i32, i32), direction: &RobotDirection) {
-        println!("\x1b[{}F", self.height + 1);
-        for y in 0..self.height {
-            for x in 0..self.width {
-                let point = if position == &(x, y) {
-                    direction.to_char()
-                } else if self.points.contains(&(x, y)) {
-                    '#'
-                } else {
-                    '.'
-                };
-                print!("{}", point);
-            }
-            println!("");
         }
     }
 
@@ -204,7 +141,6 @@ impl ScaffoldingMap {
         let mut path = vec![];
         let mut forward_moves = 0;
         loop {
-            self.render_with_robot(&robot_position, &robot_direction);
             // sleep(Duration::from_millis(5));
             let next_point = robot_direction.move_point(&robot_position);
             if self.points.contains(&next_point) {
@@ -221,7 +157,7 @@ impl ScaffoldingMap {
                     path.push(forward_moves.to_string());
                 }
                 path.push("L".to_string());
-                forward_moves = 0;
+                forward_moves = 1;
                 continue;
             }
             let next_right = right.move_point(&robot_position);
@@ -231,8 +167,8 @@ impl ScaffoldingMap {
                 if forward_moves > 0 {
                     path.push(forward_moves.to_string());
                 }
-                path.push("L".to_string());
-                forward_moves = 0;
+                path.push("R".to_string());
+                forward_moves = 1;
                 continue;
             }
             path.push(forward_moves.to_string());
@@ -242,74 +178,124 @@ impl ScaffoldingMap {
     }
 }
 
-fn build_path(path: &Vec<String>) -> Vec<(String, usize)> {
-    let path_string = path.join(",");
-    let mut repeating = HashSet::new();
-    for i in 2..5 {
-        let strings = path
-            .windows(i)
-            .filter_map(|slice| {
-                let sub = slice.join(",");
-                if sub.len() < 19 {
-                    Some(sub)
-                } else {
-                    None
-                }
-            });
-        for slice in strings {
-            repeating.insert(slice);
+const MAX_ROUTINE_LENGTH: usize = 20;
+const MAX_FUNCTIONS: usize = 3;
+
+/// Splits `moves` into calls of at most three functions, appending to `functions` and `routine`
+fn fill_routine<'a>(moves: &'a [String], functions: &mut Vec<&'a [String]>, routine: &mut Vec<usize>) -> bool {
+    if moves.is_empty() {
+        return true;
+    }
+    // each call takes a letter and a comma
+    if (routine.len() + 1) * 2 - 1 > MAX_ROUTINE_LENGTH {
+        return false;
+    }
+    for (index, function) in functions.clone().iter().enumerate() {
+        if moves.starts_with(function) {
+            routine.push(index);
+            if fill_routine(&moves[function.len()..], functions, routine) {
+                return true;
+            }
+            routine.pop();
         }
     }
-    let mut repeating_paths: Vec<(String, usize)> = repeating
-                                        .iter()
-                                        .map(|s| (s.to_owned(), path_string.matches(s).count()))
-                                        .collect();
-    repeating_paths.sort_by_key(|(_, l)| *l);
-    repeating_paths.reverse();
-    repeating_paths
+    if functions.len() < MAX_FUNCTIONS {
+        for length in 1..=moves.len() {
+            let function = &moves[..length];
+            if function.join(",").len() > MAX_ROUTINE_LENGTH {
+                break;
+            }
+            functions.push(function);
+            routine.push(functions.len() - 1);
+            if fill_routine(&moves[length..], functions, routine) {
+                return true;
+            }
+            routine.pop();
+            functions.pop();
+        }
+    }
+    false
 }
 
-pub fn two() {
-    let mut computer = IntcodeComputer::new_from_file("input/day_seventeen.txt");
-    computer.write_memory(0, 2, ParamMode::PositionMode).unwrap();
-    computer.run_ignore_output().unwrap();
-
+/// The main routine followed by functions A, B and C that walk `path`
+fn compress_path(path: &[String]) -> Option<Vec<String>> {
+    let moves: Vec<String> = path.chunks(2).map(|step| step.join(",")).collect();
+    let mut functions = vec![];
+    let mut routine = vec![];
+    if !fill_routine(&moves, &mut functions, &mut routine) {
+        return None;
+    }
+    let main = routine
+        .iter()
+        .map(|index| ((b'A' + *index as u8) as char).to_string())
+        .collect::<Vec<String>>()
+        .join(",");
+    let mut lines = vec![main];
+    lines.extend(functions.iter().map(|function| function.join(",")));
+    // the robot asks for all three even when fewer are called
+    while lines.len() <= MAX_FUNCTIONS {
+        lines.push(lines[1].clone());
+    }
+    Some(lines)
+}
 
-    let output = computer.pop_output();
-    
-    let map: String = output.iter().map(|c| *c as u8 as char).take_while(|c| !c.is_alphabetic()).collect();
-    let scaffolding_map = ScaffoldingMap::new(&map);
-    scaffolding_map.render(false);
-    let path = scaffolding_map.search();
-    let text: String = path.iter().map(|s| s.to_owned()).collect();
-    println!("{:?}", text);
-    println!("{:?}", path);
-    println!("\n{:?}", build_path(&path));
-    // println!("{}", text);
-    // println!("{}", text.matches("L7L11L11").count());
-    // println!("{}", text.replace("L7L11L11", " "));
-    // println!("{}", text.replace("L7L11L11", " ").matches("L7").count());
-    // println!("{}", text.replace("L7", " "));
-    // println!("{}", text.matches("L9").count());
-    // println!("{}", text.replace("L9", " "));
-    // println!("{}", text.replace("L7L11L11", "A").replace("L9", "B").replace("L7", "C"));
+fn collected_dust(input: &str) -> Result<i64, SolveError> {
+    let mut computer = IntcodeComputer::new(parse_program(input));
+    computer.write_memory(0, 2, ParamMode::PositionMode)?;
+    let mut computer = AsciiComputer::new(computer);
+    let text = computer.read_until_prompt()?;
+    let map = text.split("Main:").next().unwrap_or_default();
+    let path = ScaffoldingMap::new(map).search();
+    let routines = compress_path(&path)
+        .ok_or_else(|| SolveError::NoAnswer("path doesn't fit three routines".to_owned()))?;
+    for line in &routines {
+        computer.send_line(line)?;
+    }
+    // no continuous video feed
+    computer.send_line("n")?;
+    computer.read_until_prompt()?;
+    computer
+        .take_non_ascii()
+        .pop()
+        .ok_or_else(|| SolveError::NoAnswer("robot didn't report any dust".to_owned()))
+}
 
-    // let text: String = output.iter().map(|c| *c as u8 as char).collect();
-    // println!("\n\n{}", text);
+pub struct DaySeventeen;
 
-    computer.provide_input_iter("A\n".chars().map(|c| c as i64));
+impl Solution for DaySeventeen {
+    fn part1(&self, input: &str) -> Result<Answer, SolveError> {
+        Ok(Answer::Number(sum_of_alignments(input)? as i64))
+    }
 
-    computer.run_ignore_output().unwrap();
-    // let output = computer.pop_output();
-    // let text: String = output.iter().map(|c| *c as u8 as char).collect();
-    // println!("2 {}", text);
+    fn part2(&self, input: &str) -> Result<Answer, SolveError> {
+        Ok(Answer::Number(collected_dust(input)?))
+    }
+}
 
-    computer.provide_input_iter("0\n0\n0\ny\n".chars().map(|c| c as i64));
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::puzzle_input::embedded;
 
-    computer.run_ignore_output().unwrap();
-    // let output = computer.pop_output();
-    // let text: String = output.iter().map(|c| *c as u8 as char).collect();
-    // println!("3 {}", text);
+    #[test]
+    fn compresses_example_path() {
+        let path: Vec<String> = "R,8,R,8,R,4,R,4,R,8,L,6,L,2,R,4,R,4,R,8,R,8,R,8,L,6,L,2"
+            .split(',')
+            .map(|step| step.to_owned())
+            .collect();
+        let lines = compress_path(&path).unwrap();
+        assert!(lines.iter().all(|line| line.len() <= MAX_ROUTINE_LENGTH));
+        let expanded: Vec<&str> = lines[0]
+            .split(',')
+            .map(|call| lines[(call.as_bytes()[0] - b'A') as usize + 1].as_str())
+            .collect();
+        assert_eq!(expanded.join(","), path.join(","));
+    }
 
-    // println!("4 {}", computer.pop_output().iter().last().unwrap());
-}
\ No newline at end of file
+    #[test]
+    fn day_17_answers() {
+        let input = embedded(17).unwrap();
+        assert_eq!(DaySeventeen.part1(input).unwrap(), Answer::Number(10632));
+        assert_eq!(DaySeventeen.part2(input).unwrap(), Answer::Number(1356191));
+    }
+}
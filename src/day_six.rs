@@ -1,16 +1,16 @@
-use std::fs;
 use std::collections::{HashMap, VecDeque, HashSet};
 use std::error;
+use crate::solution::{Answer, Solution, SolveError};
 
-fn read_file(path: &str) -> String {
-    fs::read_to_string(path).expect("Something went wrong reading the file")
-}
-
-fn parse_input(input: &str) -> Vec<(&str, &str)> {
+fn parse_input(input: &str) -> Result<Vec<(&str, &str)>, SolveError> {
     input
         .lines()
-        .map(|line| line.split(")"))
-        .map(|mut iter| (iter.next().unwrap(), iter.next().unwrap()))
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+        .map(|line| match line.split_once(')') {
+            Some((parent, child)) if !parent.is_empty() && !child.is_empty() => Ok((parent, child)),
+            _ => Err(SolveError::Parse(format!("{:?} isn't an orbit", line))),
+        })
         .collect()
 }
 
@@ -66,35 +66,37 @@ fn count_orbits_for_key(graph: &HashMap<&str, Vec<&str>>, node: &str, count: i32
     counter
 }
 
-pub fn one() {
-    let text = read_file("input/day_six.txt");
-    let data = parse_input(&text);
-    let graph = edges_to_directed_graph(&data);
-    let count = count_orbits(&graph);
-    println!("{}", count);
-}
+pub struct DaySix;
 
-pub fn two() {
-    let text = read_file("input/day_six.txt");
-    let parsed = parse_input(&text);
-    let graph = edges_to_undirected_graph(&parsed);
-    let distance = breath_first_search(&graph, "YOU", "SAN");
-    if let Ok(distance) = distance {
-        println!("Distance between you and santa {}",distance - 2);
+impl Solution for DaySix {
+    fn part1(&self, input: &str) -> Result<Answer, SolveError> {
+        let data = parse_input(input)?;
+        let graph = edges_to_directed_graph(&data);
+        Ok(Answer::Number(count_orbits(&graph) as i64))
+    }
+
+    fn part2(&self, input: &str) -> Result<Answer, SolveError> {
+        let parsed = parse_input(input)?;
+        let graph = edges_to_undirected_graph(&parsed);
+        let distance = breath_first_search(&graph, "YOU", "SAN")?;
+        // the orbits of YOU and SAN themselves don't count as transfers
+        Ok(Answer::Number((distance - 2) as i64))
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::puzzle_input::embedded;
     
     #[test]
     fn parse_input_simple_tuple() {
         let test_input = "A)B\nB)C";
-        let parsed = parse_input(test_input);
+        let parsed = parse_input(test_input).unwrap();
         let mut parsed_iter = parsed.iter();
         assert_eq!(parsed_iter.next(), Some(&("A", "B")));
         assert_eq!(parsed_iter.next(), Some(&("B", "C")));
+        assert!(matches!(parse_input("A)B\nBC"), Err(SolveError::Parse(_))));
     }
 
     #[test]
@@ -109,7 +111,7 @@ mod tests {
     #[test]
     fn orbit_parse_count_test() {
         let text_input = "COM)B\nB)C\nC)D\nD)E\nE)F\nB)G\nG)H\nD)I\nE)J\nJ)K\nK)L";
-        let parsed = parse_input(text_input);
+        let parsed = parse_input(text_input).unwrap();
         let graph = edges_to_directed_graph(&parsed);
         let count = count_orbits(&graph);
         assert_eq!(count, 42);
@@ -117,8 +119,8 @@ mod tests {
 
     #[test]
     fn day_6_task_1() {
-        let text = read_file("input/day_six.txt");
-        let data = parse_input(&text);
+        let text = embedded(6).unwrap();
+        let data = parse_input(text).unwrap();
         let graph = edges_to_directed_graph(&data);
         let count = count_orbits(&graph);
         assert_eq!(count, 147807);
@@ -127,7 +129,7 @@ mod tests {
     #[test]
     fn breath_search_finds_example() {
         let text_input = "COM)B\nB)C\nC)D\nD)E\nE)F\nB)G\nG)H\nD)I\nE)J\nJ)K\nK)L\nK)YOU\nI)SAN";
-        let parsed = parse_input(text_input);
+        let parsed = parse_input(text_input).unwrap();
         let graph = edges_to_undirected_graph(&parsed);
         let distance = breath_first_search(&graph, "YOU", "SAN").unwrap();
         assert_eq!(distance, 6);
@@ -135,11 +137,12 @@ mod tests {
 
     #[test]
     fn day_6_task_2() {
-        let text = read_file("input/day_six.txt");
-        let parsed = parse_input(&text);
+        let text = embedded(6).unwrap();
+        let parsed = parse_input(text).unwrap();
         let graph = edges_to_undirected_graph(&parsed);
         let distance = breath_first_search(&graph, "YOU", "SAN").unwrap();
         // 229 + 2 because you are adding the orbit of YOU and SAN from the distance
         assert_eq!(distance, 229 + 2);
+        assert_eq!(DaySix.part2(text).unwrap(), Answer::Number(229));
     }
 }
@@ -1,10 +1,8 @@
-use crate::infinite_memory_intcomputer::{IntcodeComputer, IntcodeComputerState::{OutputProduced, WaitingForInput}, ParamMode::PositionMode};
+use crate::infinite_memory_intcomputer::{parse_program, IntcodeComputer, IntcodeComputerState::{OutputProduced, WaitingForInput}, ParamMode::PositionMode};
+use crate::solution::{Answer, Solution, SolveError};
 use std::collections::HashMap;
 use std::error::*;
-use std::{thread, time};
 
-const EMPTY: i32 = 0;
-const WALL: i32 = 1;
 const BLOCK: i32 = 2;
 const HORIZONTAL_PADDLE: i32 = 3;
 const BALL: i32 = 4;
@@ -17,16 +15,16 @@ struct ArcadeCAbinet {
 }
 
 impl ArcadeCAbinet {
-    fn new() -> ArcadeCAbinet {
+    fn new(input: &str) -> ArcadeCAbinet {
         ArcadeCAbinet {
-            computer: IntcodeComputer::new_from_file("input/day_thirteen.txt"),
+            computer: IntcodeComputer::new(parse_program(input)),
             display: HashMap::new(),
             score: 0,
         }
     }
 
     fn insert_coins(&mut self) -> Result<(), Box<dyn Error>> {
-        self.computer.write_memory(0, 2, PositionMode)
+        Ok(self.computer.write_memory(0, 2, PositionMode)?)
     }
 
     fn input(&mut self, input: i32) {
@@ -67,97 +65,57 @@ impl ArcadeCAbinet {
     }
 }
 
-pub fn one() {
-    let mut cabinet = ArcadeCAbinet::new();
-    cabinet.run().unwrap();
-    let display = cabinet.display.clone();
-    let mut block_count = 0;
-    for (_, pixel) in &display {
-        if pixel == &BLOCK {
-            block_count += 1;
+/// Where the ball and the paddle are on the screen
+fn ball_and_paddle(frame: &HashMap<(i32, i32), i32>) -> ((i32, i32), (i32, i32)) {
+    let mut ball = (0, 0);
+    let mut paddle = (0, 0);
+    for (point, tile) in frame {
+        match *tile {
+            HORIZONTAL_PADDLE => paddle = *point,
+            BALL => ball = *point,
+            _ => (),
         }
     }
-    println!("{}", block_count);
+    (ball, paddle)
 }
 
-fn render_frame(frame: &HashMap<(i32, i32), i32>) -> ((i32, i32), (i32, i32), String, i32) {
-    let mut top_left = (std::i32::MAX, std::i32::MAX);
-    let mut bottom_right = (std::i32::MIN, std::i32::MIN);
-    for point in frame.keys() {
-        if point.0 < top_left.0 {
-            top_left.0 = point.0
-        }
-        if point.1 < top_left.1 {
-            top_left.1 = point.1
-        }
-        if point.0 > bottom_right.0 {
-            bottom_right.0 = point.0
-        }
-        if point.1 > bottom_right.1 {
-            bottom_right.1 = point.1
-        }
-    }
+pub struct DayThirteen;
 
-    let mut ball = (0, 0);
-    let mut paddle = (0, 0);
-    let mut line_count = 0;
-    let mut buffer = String::new();
-    for y in top_left.1..(bottom_right.1+1) {
-        for x in top_left.0..(bottom_right.0+1) {
-            let pixel = frame.get(&(x, y)).unwrap_or(&EMPTY);
-            match pixel {
-                &EMPTY => buffer.push_str(" "),
-                &WALL => buffer.push_str("|"),
-                &BLOCK => buffer.push_str("❤️"),
-                &HORIZONTAL_PADDLE => {
-                    buffer.push_str("_");
-                    paddle = (x, y);
-                },
-                &BALL => {
-                    buffer.push_str("O");
-                    ball = (x, y);
-                },
-                _ => panic!("unknown symbol"),
-            }
-        }
-        buffer.push_str("               \n");
-        line_count+=1;
+impl Solution for DayThirteen {
+    fn part1(&self, input: &str) -> Result<Answer, SolveError> {
+        let mut cabinet = ArcadeCAbinet::new(input);
+        cabinet.run()?;
+        let block_count = cabinet.display.values().filter(|pixel| **pixel == BLOCK).count();
+        Ok(Answer::Number(block_count as i64))
     }
-    // println!("{}", buffer);
-    (ball, paddle, buffer, line_count)
-}
 
-pub fn two() {
-    let mut cabinet = ArcadeCAbinet::new();
-    cabinet.insert_coins().unwrap();
-    let mut prev_lines = None;
-    while cabinet.run_game().unwrap() {
-        if let Some(line_count) = prev_lines {
-            println!("\x1b[{}F", line_count+2);
-        }
-        let (ball, paddle, frame, lines) = render_frame(&cabinet.display);
-        prev_lines = Some(lines);
-        println!("{}", frame);
-        thread::sleep(time::Duration::from_millis(1));
-        // Smarty AI
-        if ball.0 > paddle.0 {
-            cabinet.input(1);
-        } else if ball.0 < paddle.0 {
-            cabinet.input(-1)
-        } else {
-            cabinet.input(0)
+    fn part2(&self, input: &str) -> Result<Answer, SolveError> {
+        let mut cabinet = ArcadeCAbinet::new(input);
+        cabinet.insert_coins()?;
+        while cabinet.run_game()? {
+            let (ball, paddle) = ball_and_paddle(&cabinet.display);
+            // Smarty AI
+            if ball.0 > paddle.0 {
+                cabinet.input(1);
+            } else if ball.0 < paddle.0 {
+                cabinet.input(-1)
+            } else {
+                cabinet.input(0)
+            }
         }
+        Ok(Answer::Number(cabinet.score as i64))
     }
-    println!("your score is {}", cabinet.score);
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::puzzle_input::embedded;
 
     #[test]
     fn day_13_task_1() {
-        let mut cabinet = ArcadeCAbinet::new();
+        let input = embedded(13).unwrap();
+        let mut cabinet = ArcadeCAbinet::new(input);
         cabinet.run().unwrap();
         let display = cabinet.display.clone();
         let mut block_count = 0;
@@ -171,10 +129,11 @@ mod tests {
 
     #[test]
     fn day_13_task_2() {
-        let mut cabinet = ArcadeCAbinet::new();
+        let input = embedded(13).unwrap();
+        let mut cabinet = ArcadeCAbinet::new(input);
         cabinet.insert_coins().unwrap();
         while cabinet.run_game().unwrap() {
-            let (ball, paddle, _, _) = render_frame(&cabinet.display);
+            let (ball, paddle) = ball_and_paddle(&cabinet.display);
             // Smarty AI
             if ball.0 > paddle.0 {
                 cabinet.input(1);
@@ -185,5 +144,6 @@ mod tests {
             }
         }
         assert_eq!(12765, cabinet.score);
+        assert_eq!(DayThirteen.part2(input).unwrap(), Answer::Number(12765));
     }
 }
\ No newline at end of file
@@ -1,10 +1,8 @@
-use std::fs;
 use std::collections::HashSet;
 use std::collections::HashMap;
+use crate::solution::{Answer, Solution, SolveError};
 
-fn get_input() -> Vec<Vec<String>> {
-    let input = fs::read_to_string("input/day_three.txt")
-        .expect("Something went wrong reading the file");
+fn parse_input(input: &str) -> Vec<Vec<String>> {
     let lines = input.lines();
     let mut results = vec![];
     for line in lines {
@@ -56,27 +54,59 @@ fn translate_to_points(directions: &Vec<String>) -> Vec<((i32, i32), i32)> {
     path
 }
 
-pub fn one() {
-    let data = get_input();
-    let points_a: HashSet<_> = translate_to_points(&data[0]).iter().map(|a| a.0).collect();
-    let points_b: HashSet<_> = translate_to_points(&data[1]).iter().map(|a| a.0).collect();
-    let intersect = points_a.intersection(&points_b);
+fn wires(input: &str) -> Result<(Vec<((i32, i32), i32)>, Vec<((i32, i32), i32)>), SolveError> {
+    match parse_input(input).as_slice() {
+        [a, b] => Ok((translate_to_points(a), translate_to_points(b))),
+        _ => Err(SolveError::Parse("expected two wires".to_owned())),
+    }
+}
+
+pub struct DayThree;
+
+impl Solution for DayThree {
+    fn part1(&self, input: &str) -> Result<Answer, SolveError> {
+        let (wire_a, wire_b) = wires(input)?;
+        let points_a: HashSet<_> = wire_a.iter().map(|a| a.0).collect();
+        let points_b: HashSet<_> = wire_b.iter().map(|a| a.0).collect();
+        let intersect = points_a.intersection(&points_b);
+
+        let closest = intersect
+            .min_by_key(|(a, b)| a.abs() + b.abs())
+            .ok_or_else(|| SolveError::NoAnswer("wires don't cross".to_owned()))?;
+        Ok(Answer::Number((closest.0.abs() + closest.1.abs()) as i64))
+    }
 
-    let closest = intersect.min_by_key(|(a, b)| a.abs() + b.abs()).unwrap();
-    println!("closest is {:?} with distance {}", closest, closest.0.abs() + closest.1.abs());
+    fn part2(&self, input: &str) -> Result<Answer, SolveError> {
+        let (wire_a, wire_b) = wires(input)?;
+        let points_a: HashMap<(i32, i32), i32> = wire_a.into_iter().collect();
+        let points_b: HashMap<(i32, i32), i32> = wire_b.into_iter().collect();
+        let set_a = points_a.keys().collect::<HashSet<_>>();
+        let set_b = points_b.keys().collect::<HashSet<_>>();
+        let intersect = set_a.intersection(&set_b);
+
+        let closest = intersect
+            .min_by_key(|point| points_a[point].abs() + points_b[point].abs())
+            .ok_or_else(|| SolveError::NoAnswer("wires don't cross".to_owned()))?;
+        Ok(Answer::Number((points_a[closest].abs() + points_b[closest].abs()) as i64))
+    }
 }
 
-pub fn two() {
-    let data = get_input();
-    let points_a: HashMap<(i32, i32), i32> = translate_to_points(&data[0]).iter().cloned().collect();
-    let points_b: HashMap<(i32, i32), i32> = translate_to_points(&data[1]).iter().cloned().collect();
-    let set_a = points_a.keys().collect::<HashSet<_>>();
-    let set_b = points_b.keys().collect::<HashSet<_>>();
-    let intersect = set_a.intersection(&set_b);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::puzzle_input::embedded;
 
-    let closest = intersect.min_by_key(|point| points_a[point].abs() + points_b[point].abs()).unwrap();
-    println!("closest is {:?} with distance {} step distance {}",
-            closest,
-            closest.0.abs() + closest.1.abs(),
-            points_a[closest].abs() + points_b[closest].abs());
+    #[test]
+    fn crossing_wires_example() {
+        let input = "R8,U5,L5,D3\nU7,R6,D4,L4";
+        assert_eq!(DayThree.part1(input).unwrap(), Answer::Number(6));
+        assert_eq!(DayThree.part2(input).unwrap(), Answer::Number(30));
+    }
+
+    #[test]
+    fn day_3_answers() {
+        let input = embedded(3).unwrap();
+        assert_eq!(DayThree.part1(input).unwrap(), Answer::Number(651));
+        assert_eq!(DayThree.part2(input).unwrap(), Answer::Number(7534));
+    }
 }
@@ -1,14 +1,9 @@
-use std::fs;
 use std::ops::Add;
 use std::fmt;
 use std::collections::{HashMap, HashSet};
+use crate::solution::{parse_number, Answer, Solution, SolveError};
 
-fn get_input() -> String {
-    fs::read_to_string("input/day_twelve.txt")
-        .expect("Something went wrong reading the file")
-}
-
-fn parse_input(text: &str) -> Vec<Moon> {
+fn parse_input(text: &str) -> Result<Vec<Moon>, SolveError> {
     let mut moons = vec![];
     let text = text.replace("<", "")
             .replace(">", "")
@@ -17,14 +12,13 @@ fn parse_input(text: &str) -> Vec<Moon> {
             .replace("z", "")
             .replace("=", "");
     for line in text.lines() {
-        let mut split = line.split(",");
-        let x: i32 = split.next().unwrap().trim().parse().unwrap();
-        let y: i32 = split.next().unwrap().trim().parse().unwrap();
-        let z: i32 = split.next().unwrap().trim().parse().unwrap();
-
-        moons.push(Moon::new(Vector::new(x, y, z)));
+        let mut split = line.split(",").map(parse_number);
+        match (split.next(), split.next(), split.next()) {
+            (Some(x), Some(y), Some(z)) => moons.push(Moon::new(Vector::new(x?, y?, z?))),
+            _ => return Err(SolveError::Parse(format!("{:?} isn't a position", line))),
+        }
     }
-    moons
+    Ok(moons)
 }
 
 #[derive(Clone, PartialEq, Eq, Hash)]
@@ -172,12 +166,19 @@ fn simulate_axis_until_repeat(moons: &Vec<Moon>) -> i32 {
     }
 }
 
-pub fn one() {
-    let text = get_input();
-    let moons = parse_input(&text);
-    let moons = simulate_moons(&moons, 1000);
-    let system_energy: i32 = moons.iter().map(|moon| moon.total_energy()).sum();
-    println!("{}", system_energy)
+pub struct DayTwelve;
+
+impl Solution for DayTwelve {
+    fn part1(&self, input: &str) -> Result<Answer, SolveError> {
+        let moons = parse_input(input)?;
+        let moons = simulate_moons(&moons, 1000);
+        let system_energy: i32 = moons.iter().map(|moon| moon.total_energy()).sum();
+        Ok(Answer::Number(system_energy as i64))
+    }
+
+    fn part2(&self, _input: &str) -> Result<Answer, SolveError> {
+        Err(SolveError::Unsolved)
+    }
 }
 
 // pub fn two() {
@@ -192,13 +193,14 @@ pub fn one() {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::puzzle_input::embedded;
 
     #[test]
     fn day_12_test_1() {
         let moons = parse_input("<x=-1, y=0, z=2>
         <x=2, y=-10, z=-7>
         <x=4, y=-8, z=8>
-        <x=3, y=5, z=-1>");
+        <x=3, y=5, z=-1>").unwrap();
         let moons = simulate_moons(&moons, 10);
         let system_energy: i32 = moons.iter().map(|moon| moon.total_energy()).sum();
         assert_eq!(system_energy, 179);
@@ -209,17 +211,14 @@ mod tests {
         let moons = parse_input("<x=-8, y=-10, z=0>
         <x=5, y=5, z=10>
         <x=2, y=-7, z=3>
-        <x=9, y=-8, z=-3>");
+        <x=9, y=-8, z=-3>").unwrap();
         let moons = simulate_moons(&moons, 100);
         let system_energy: i32 = moons.iter().map(|moon| moon.total_energy()).sum();
         assert_eq!(system_energy, 1940);
     }
 
     fn day_12_task_1() {
-        let text = get_input();
-        let moons = parse_input(&text);
-        let moons = simulate_moons(&moons, 1000);
-        let system_energy: i32 = moons.iter().map(|moon| moon.total_energy()).sum();
-        assert_eq!(5517, system_energy)
+        let text = embedded(12).unwrap();
+        assert_eq!(DayTwelve.part1(text).unwrap(), Answer::Number(5517));
     }
 }
\ No newline at end of file
@@ -1,11 +1,5 @@
-use std::fs;
 use std::collections::{HashMap, VecDeque, HashSet};
-use std::time::Instant;
-
-fn get_input() -> String {
-    fs::read_to_string("input/day_twenty.txt")
-        .expect("Something went wrong reading the file")
-}
+use crate::solution::{Answer, Solution, SolveError};
 
 const WALL: char = '#';
 const EMPTY: char = '.';
@@ -19,7 +13,6 @@ struct Map {
     end: Option<(i32, i32)>,
     top_left: (i32, i32),
     bottom_right: (i32, i32),
-    last_drawn_lines: Option<i32>,
 }
 
 impl Map {
@@ -31,7 +24,6 @@ impl Map {
             end: None,
             top_left: (std::i32::MAX, std::i32::MAX),
             bottom_right: (std::i32::MIN, std::i32::MIN),
-            last_drawn_lines: None,
         }
     }
 
@@ -55,53 +47,6 @@ impl Map {
         *self.points.get(point).unwrap_or(&NOTHING)
     }
 
-    fn contains(&self, point: &(i32, i32)) -> bool {
-        self.points.contains_key(point)
-    }
-
-    fn render(&mut self) {
-        // if let Some(lines) = self.last_drawn_lines {
-        //     println!("\x1b[{}F", lines);
-        // }
-        let mut lines = 0;
-        for y in self.top_left.1..(self.bottom_right.1+1) {
-            for x in self.top_left.0..(self.bottom_right.0+1) {
-                let pixel = self.points.get(&(x, y)).unwrap_or(&NOTHING);
-                let pixel = if pixel == &PORTAL {
-                    if self.is_edge_portal(&(x, y)) {
-                        '1'
-                    } else {
-                        '2'
-                    }
-                } else {
-                    *pixel
-                };
-                print!("{}", pixel);
-            }
-            lines+=1;
-            println!("");
-        }
-        self.last_drawn_lines = Some(lines);
-    }
-
-    fn render_with_path(&mut self, path: &Vec<(i32, i32)>) {
-        let path: HashSet<(i32, i32)> = path.iter().map(|e| e.clone()).collect();
-        let mut lines = 0;
-        for y in self.top_left.1..(self.bottom_right.1+1) {
-            for x in self.top_left.0..(self.bottom_right.0+1) {
-                let pixel = if path.contains(&(x, y)) {
-                    &'x'
-                } else {
-                    self.points.get(&(x, y)).unwrap_or(&NOTHING)
-                };
-                print!("{}", pixel);
-            }
-            lines+=1;
-            println!("");
-        }
-        self.last_drawn_lines = Some(lines);
-    }
-
     fn scan_portals(&mut self) {
         let mut portal_map = HashMap::new();
         for y in 0..self.height() {
@@ -287,8 +232,7 @@ impl Map {
 }
 
 
-pub fn one() {
-    let input = get_input();
+fn parse_maze(input: &str) -> Map {
     let mut map = Map::new();
 
     for (y, line) in input.lines().enumerate() {
@@ -298,50 +242,39 @@ pub fn one() {
             }
         }
     }
-    map.render();
     map.scan_portals();
     map.mark_portals();
-    map.render();
+    map
+}
 
-    println!("height {}", map.height());
-    println!("width {}", map.width());
-    let path = map.bfs().unwrap();
-    map.render_with_path(&path);
-    println!("{:?}", path.len()-2);
+/// Steps between the entrance and exit, the path includes both
+fn steps(path: Option<Vec<(i32, i32)>>) -> Result<Answer, SolveError> {
+    match path {
+        Some(path) => Ok(Answer::Number(path.len() as i64 - 2)),
+        None => Err(SolveError::NoAnswer("exit isn't reachable".to_owned())),
+    }
 }
 
-pub fn two() {
-    let input = get_input();
-    let mut map = Map::new();
+pub struct DayTwenty;
 
-    for (y, line) in input.lines().enumerate() {
-        for (x, letter) in line.chars().enumerate() {
-            if letter != ' ' {
-                map.insert((x as i32, y as i32), letter);
-            }
-        }
+impl Solution for DayTwenty {
+    fn part1(&self, input: &str) -> Result<Answer, SolveError> {
+        steps(parse_maze(input).bfs())
     }
-    map.render();
-    map.scan_portals();
-    map.mark_portals();
-    map.render();
 
-    println!("height {}", map.height());
-    println!("width {}", map.width());
-    let start = Instant::now();
-    let path = map.bfs_with_layers().unwrap();
-    map.render_with_path(&path);
-    println!("{:?}", path.len()-2);
-    println!("Took {} seconds", start.elapsed().as_secs_f32());
+    fn part2(&self, input: &str) -> Result<Answer, SolveError> {
+        steps(parse_maze(input).bfs_with_layers())
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    
+    use crate::puzzle_input::embedded;
+
     #[test]
     fn day_20_task_1(){
-        let input = get_input();
+        let input = embedded(20).unwrap();
         let mut map = Map::new();
 
         for (y, line) in input.lines().enumerate() {
@@ -359,7 +292,7 @@ mod tests {
 
     #[test]
     fn day_20_task_2(){
-        let input = get_input();
+        let input = embedded(20).unwrap();
         let mut map = Map::new();
 
         for (y, line) in input.lines().enumerate() {
@@ -373,5 +306,6 @@ mod tests {
         map.mark_portals();
         let path = map.bfs_with_layers().unwrap();
         assert_eq!(7758, path.len()-2);
+        assert_eq!(DayTwenty.part2(input).unwrap(), Answer::Number(7758));
     }
 }
\ No newline at end of file
@@ -1,67 +1,97 @@
-use std::fs;
+use crate::solution::{parse_number, Answer, Solution, SolveError};
+use std::convert::TryFrom;
 
-fn get_input() -> Vec<i32> {
-    fs::read_to_string("input/day_two.txt")
-        .expect("Something went wrong reading the file")
-        .split(",")
-        .filter_map(|s| s.parse::<i32>().ok())
-        .collect()
+fn parse_input(input: &str) -> Result<Vec<i32>, SolveError> {
+    let program = input.trim().split(',').map(parse_number).collect::<Result<Vec<i32>, _>>()?;
+    if program.len() < 4 {
+        return Err(SolveError::Parse(format!("program has {} values, expected at least 4", program.len())));
+    }
+    Ok(program)
+}
+
+fn value_at(program: &[i32], position: usize) -> Result<i32, SolveError> {
+    program
+        .get(position)
+        .copied()
+        .ok_or_else(|| SolveError::Parse(format!("position {} is outside the program", position)))
+}
+
+fn address_at(program: &[i32], position: usize) -> Result<usize, SolveError> {
+    let address = value_at(program, position)?;
+    match usize::try_from(address) {
+        Ok(address) if address < program.len() => Ok(address),
+        _ => Err(SolveError::Parse(format!("address {} at {} is outside the program", address, position))),
+    }
 }
 
-pub fn process_intcode(program: &mut Vec<i32>) {
+pub fn process_intcode(program: &mut [i32]) -> Result<(), SolveError> {
     let mut program_counter = 0;
     loop {
-        let op = program[program_counter];
+        let op = value_at(program, program_counter)?;
         match op {
-            1 => {
-                // addition
-                let a = program[program[program_counter + 1] as usize];
-                let b = program[program[program_counter + 2] as usize];
-                let output_location = program[program_counter + 3] as usize;
-                program[output_location] = a + b;
-                program_counter += 4;
-
-            },
-            2 => {
-                // multiplication
-                let a = program[program[program_counter + 1] as usize];
-                let b = program[program[program_counter + 2] as usize];
-                let output_location = program[program_counter + 3] as usize;
-                program[output_location] = a * b;
+            1 | 2 => {
+                let a = program[address_at(program, program_counter + 1)?];
+                let b = program[address_at(program, program_counter + 2)?];
+                let output_location = address_at(program, program_counter + 3)?;
+                // 1 is addition, 2 multiplication
+                let result = if op == 1 { a.checked_add(b) } else { a.checked_mul(b) };
+                program[output_location] = result
+                    .ok_or_else(|| SolveError::Parse(format!("overflow at {}", program_counter)))?;
                 program_counter += 4;
             },
             99 => {
-                break;
+                return Ok(());
             },
-            _ => panic!("Error at {}", program_counter),
+            _ => return Err(SolveError::Parse(format!("unknown opcode {} at {}", op, program_counter))),
         }
     }
 }
 
-pub fn one() {
-    let mut program = get_input();
+pub struct DayTwo;
 
-    // set initial state
-    program[1] = 12;
-    program[2] = 2;
+impl Solution for DayTwo {
+    fn part1(&self, input: &str) -> Result<Answer, SolveError> {
+        let mut program = parse_input(input)?;
 
-    process_intcode(&mut program);
+        // set initial state
+        program[1] = 12;
+        program[2] = 2;
 
-    println!("Done. Position 0 has value {}", program[0]);
-}
+        process_intcode(&mut program)?;
+
+        Ok(Answer::Number(program[0] as i64))
+    }
 
-pub fn two() {
-    let program = get_input();
+    fn part2(&self, input: &str) -> Result<Answer, SolveError> {
+        let program = parse_input(input)?;
 
-    for noun in 0..100 {
-        for verb in 0..100 {
-            let mut current_program = program.clone();
-            current_program[1] = noun;
-            current_program[2] = verb;
-            process_intcode(&mut current_program);
-            if current_program[0] == 19690720 {
-                println!("Works for noun {} verb {} => code is {}", noun, verb, 100 * noun + verb);
+        for noun in 0..100 {
+            for verb in 0..100 {
+                let mut current_program = program.clone();
+                current_program[1] = noun;
+                current_program[2] = verb;
+                process_intcode(&mut current_program)?;
+                if current_program[0] == 19690720 {
+                    return Ok(Answer::Number((100 * noun + verb) as i64));
+                }
             }
         }
+        Err(SolveError::NoAnswer("no noun and verb produce 19690720".to_owned()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::puzzle_input::embedded;
+
+    #[test]
+    fn day_2_answers() {
+        let input = embedded(2).unwrap();
+        assert_eq!(DayTwo.part1(input).unwrap(), Answer::Number(4090701));
+        assert_eq!(DayTwo.part2(input).unwrap(), Answer::Number(6421));
+        assert!(matches!(DayTwo.part1("1,0"), Err(SolveError::Parse(_))));
+        assert!(matches!(DayTwo.part1("1,0,x,0,99"), Err(SolveError::Parse(_))));
+        assert!(matches!(DayTwo.part1("1,0,0,50,99"), Err(SolveError::Parse(_))));
     }
 }
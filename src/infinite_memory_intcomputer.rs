@@ -1,50 +1,211 @@
 use std::fs;
-use std::error::*;
-use std::collections::{VecDeque, HashMap};
+use std::fmt;
+use std::error::Error;
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+use std::io::{self, Read, Write};
+use std::sync::{Arc, Mutex, OnceLock};
+use crate::intcode_memory::Memory;
+use crate::intcode_disassembler::Opcode;
+use crate::intcode_io::IntcodeIo;
+use crate::intcode_profiler::Profile;
+use crate::intcode_save_state::{read_snapshot, write_snapshot, SaveStateError};
+use crate::intcode_tracer::{TraceSink, TraceStep};
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IntcodeError {
+    UnknownOpcode { op: i64, ip: i32 },
+    InvalidParamMode { mode: i64, ip: i32 },
+    NegativeAddress { addr: i64, ip: i32 },
+    ImmediateWrite { ip: i32 },
+    InputExhausted,
+    InvalidInput { text: String },
+    ValueOutOfRange { value: i64 },
+    Io { path: String, message: String },
+    /// The input file is missing or empty
+    InputNotAvailable { path: String },
+    Overflow { ip: i32 },
+    AddressOutOfRange { addr: i64, ip: i32 },
+    /// A run stopped in a state its driver never returns, e.g. `Blocked` from `run`
+    UnexpectedState(IntcodeComputerState),
+}
+
+impl fmt::Display for IntcodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IntcodeError::UnknownOpcode { op, ip } => write!(f, "Unsupported operation {} at {}", op, ip),
+            IntcodeError::InvalidParamMode { mode, ip } => write!(f, "Unknown param mode {} at {}", mode, ip),
+            IntcodeError::NegativeAddress { addr, ip } => write!(f, "Accessing memory in negative index {} at {}", addr, ip),
+            IntcodeError::ImmediateWrite { ip } => write!(f, "Wrong param mode for write at {}", ip),
+            IntcodeError::InputExhausted => write!(f, "Program requested input but none is left"),
+            IntcodeError::InvalidInput { text } => write!(f, "Input {:?} isn't a number", text),
+            IntcodeError::ValueOutOfRange { value } => write!(f, "Value {} doesn't fit in an i32", value),
+            IntcodeError::Io { path, message } => write!(f, "Failed reading {}: {}", path, message),
+            IntcodeError::InputNotAvailable { path } => write!(f, "Input {} is not available", path),
+            IntcodeError::Overflow { ip } => write!(f, "Arithmetic overflow at {}", ip),
+            IntcodeError::AddressOutOfRange { addr, ip } => write!(f, "Address {} is out of range at {}", addr, ip),
+            IntcodeError::UnexpectedState(state) => write!(f, "Run stopped with unexpected state {:?}", state),
+        }
+    }
+}
+
+impl Error for IntcodeError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum IntcodeComputerState {
     WaitingForInput,
     Halted,
     OutputProduced(i64),
+    /// `run_with_budget` used up its instructions, running again resumes
+    BudgetExhausted,
+    /// `run_with_io` needs input its IO can't provide
+    Blocked,
 }
 
-#[derive(PartialEq, Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemoryAccess {
+    Read { address: i32, value: i64 },
+    Write { address: i32, value: i64 },
+}
+
+/// What `ADD` and `MUL` do when the result doesn't fit an i64
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OverflowMode {
+    #[default]
+    Error,
+    Wrap,
+    Saturate,
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
 pub enum ParamMode {
     PositionMode,
     ImmediateMode,
     RelativeMode,
 }
 
+#[derive(Clone)]
+pub(crate) struct MachineState {
+    pub(crate) memory: Memory,
+    pub(crate) instruction_pointer: i32,
+    pub(crate) relative_base: i32,
+    pub(crate) input_queue: VecDeque<i64>,
+    pub(crate) output: Vec<i64>,
+    pub(crate) output_queue: VecDeque<i64>,
+    pub(crate) instructions_executed: u64,
+}
+
+/// Frozen machine state, shares unchanged memory pages with the computer it was taken from
+#[derive(Clone)]
+pub struct IntcodeSnapshot(Arc<MachineState>);
+
+impl IntcodeSnapshot {
+    pub(crate) fn from_state(state: MachineState) -> IntcodeSnapshot {
+        IntcodeSnapshot(Arc::new(state))
+    }
+
+    pub(crate) fn state(&self) -> &MachineState {
+        &self.0
+    }
+
+    pub fn instruction_pointer(&self) -> i32 {
+        self.0.instruction_pointer
+    }
+
+    pub fn relative_base(&self) -> i32 {
+        self.0.relative_base
+    }
+}
+
+#[derive(Clone)]
+struct Tracer {
+    sink: Arc<Mutex<dyn TraceSink + Send>>,
+    accesses: Vec<MemoryAccess>,
+}
+
+/// Addresses above this are never cached, code doesn't live that far out
+const DECODE_CACHE_LIMIT: usize = 1 << 16;
+
+/// Opcode and parameter modes of an instruction word, decoded once
+#[derive(Clone, Copy)]
+struct Decoded {
+    code: i64,
+    modes: [ParamMode; 3],
+}
+
+impl Decoded {
+    fn new(op: i64) -> Option<Decoded> {
+        let opcode = Opcode::from_code(get_op_code(&op))?;
+        let mut modes = [ParamMode::PositionMode; 3];
+        for (index, mode) in modes.iter_mut().enumerate().take(opcode.param_count()) {
+            *mode = get_param_mode(&op, index as u32)?;
+        }
+        Some(Decoded { code: opcode.code(), modes })
+    }
+}
+
+/// Parameter modes of the instruction being executed, either still encoded in the op or cached
+enum Modes {
+    Encoded(i64),
+    Decoded([ParamMode; 3]),
+}
+
+/// Whether new computers cache decoded instructions, set `INTCODE_DECODE_CACHE` to turn it on
+fn decode_cache_default() -> bool {
+    static DEFAULT: OnceLock<bool> = OnceLock::new();
+    *DEFAULT.get_or_init(|| std::env::var_os("INTCODE_DECODE_CACHE").is_some())
+}
+
 #[derive(Clone)]
 pub struct IntcodeComputer {
-    memory: HashMap<i32, i64>,
+    memory: Memory,
     instruction_pointer: i32,
     relative_base: i32,
     input_queue: VecDeque<i64>,
     output: Vec<i64>,
     output_queue: VecDeque<i64>,
+    instructions_executed: u64,
+    access_log: Option<Vec<MemoryAccess>>,
+    tracer: Option<Tracer>,
+    decode_cache: Option<Vec<Option<Decoded>>>,
+    profile: Option<Profile>,
+    overflow_mode: OverflowMode,
 }
 
 impl IntcodeComputer {
     pub fn new(program: Vec<i64>) -> IntcodeComputer {
-        let memory: HashMap<i32, i64> = program
-                                            .iter()
-                                            .enumerate()
-                                            .map(|(i, v)| (i as i32, *v))
-                                            .collect();
         IntcodeComputer {
-            memory,
+            memory: Memory::new(&program),
             instruction_pointer: 0,
             relative_base: 0,
             input_queue: VecDeque::new(),
             output: vec![],
             output_queue: VecDeque::new(),
+            instructions_executed: 0,
+            access_log: None,
+            tracer: None,
+            decode_cache: if decode_cache_default() { Some(vec![]) } else { None },
+            profile: None,
+            overflow_mode: OverflowMode::default(),
         }
     }
 
-    pub fn new_from_file(path: &str) -> IntcodeComputer {
-        IntcodeComputer::new(load_input(path))
+    pub fn new_from_file(path: &str) -> Result<IntcodeComputer, IntcodeError> {
+        Ok(IntcodeComputer::new(load_input(path)?))
+    }
+
+    pub fn from_snapshot(snapshot: &IntcodeSnapshot) -> IntcodeComputer {
+        let mut computer = IntcodeComputer::new(vec![]);
+        computer.restore(snapshot);
+        computer
+    }
+
+    pub fn save_to<W: Write>(&self, writer: W) -> Result<(), SaveStateError> {
+        write_snapshot(&self.snapshot(), writer)
+    }
+
+    pub fn load_from<R: Read>(reader: R) -> Result<IntcodeComputer, SaveStateError> {
+        Ok(IntcodeComputer::from_snapshot(&read_snapshot(reader)?))
     }
 
     pub fn provide_input(&mut self, input: i64) {
@@ -57,47 +218,177 @@ impl IntcodeComputer {
         }
     }
 
-    fn read_memory(&self, location: &i32) -> Result<i64, Box<dyn Error>> {
+    pub fn snapshot(&self) -> IntcodeSnapshot {
+        IntcodeSnapshot(Arc::new(MachineState {
+            memory: self.memory.clone(),
+            instruction_pointer: self.instruction_pointer,
+            relative_base: self.relative_base,
+            input_queue: self.input_queue.clone(),
+            output: self.output.clone(),
+            output_queue: self.output_queue.clone(),
+            instructions_executed: self.instructions_executed,
+        }))
+    }
+
+    pub fn restore(&mut self, snapshot: &IntcodeSnapshot) {
+        let state = &snapshot.0;
+        self.memory = state.memory.clone();
+        self.instruction_pointer = state.instruction_pointer;
+        self.relative_base = state.relative_base;
+        self.input_queue = state.input_queue.clone();
+        self.output = state.output.clone();
+        self.output_queue = state.output_queue.clone();
+        self.instructions_executed = state.instructions_executed;
+        if let Some(cache) = &mut self.decode_cache {
+            cache.clear();
+        }
+    }
+
+    pub fn instruction_pointer(&self) -> i32 {
+        self.instruction_pointer
+    }
+
+    /// Instructions completed so far, a halt or a read that has to wait for input doesn't count
+    pub fn instructions_executed(&self) -> u64 {
+        self.instructions_executed
+    }
+
+    pub fn relative_base(&self) -> i32 {
+        self.relative_base
+    }
+
+    pub fn input_queue(&self) -> &VecDeque<i64> {
+        &self.input_queue
+    }
+
+    pub fn output_queue(&self) -> &VecDeque<i64> {
+        &self.output_queue
+    }
+
+    pub fn peek_memory(&self, location: i32) -> Result<i64, IntcodeError> {
+        self.read_memory(&location)
+    }
+
+    /// Data reads and writes are logged while enabled, instruction fetches are not
+    pub fn record_accesses(&mut self, enabled: bool) {
+        self.access_log = if enabled { Some(vec![]) } else { None };
+    }
+
+    pub fn take_accesses(&mut self) -> Vec<MemoryAccess> {
+        match &mut self.access_log {
+            Some(log) => std::mem::take(log),
+            None => vec![],
+        }
+    }
+
+    fn log_access(&mut self, access: MemoryAccess) {
+        if let Some(log) = &mut self.access_log {
+            log.push(access);
+        }
+        if let Some(tracer) = &mut self.tracer {
+            tracer.accesses.push(access);
+        }
+        if let Some(profile) = &mut self.profile {
+            profile.count_access(&access);
+        }
+    }
+
+    pub fn set_overflow_mode(&mut self, mode: OverflowMode) {
+        self.overflow_mode = mode;
+    }
+
+    /// Caches the decoded opcode and parameter modes per address, writing to an address drops
+    /// its entry. Off by default.
+    pub fn set_decode_cache(&mut self, enabled: bool) {
+        self.decode_cache = if enabled { Some(vec![]) } else { None };
+    }
+
+    /// Counts executed instructions and memory accesses while enabled, starts a fresh profile
+    pub fn set_profiling(&mut self, enabled: bool) {
+        self.profile = if enabled { Some(Profile::default()) } else { None };
+    }
+
+    pub fn profile(&self) -> Option<&Profile> {
+        self.profile.as_ref()
+    }
+
+    /// Counts so far, profiling continues with a fresh profile
+    pub fn take_profile(&mut self) -> Option<Profile> {
+        self.profile.as_mut().map(std::mem::take)
+    }
+
+    /// Sends every executed instruction to `sink`, clones of this computer share it
+    pub fn set_tracer(&mut self, sink: Option<Arc<Mutex<dyn TraceSink + Send>>>) {
+        self.tracer = sink.map(|sink| Tracer { sink, accesses: vec![] });
+    }
+
+    fn read_memory(&self, location: &i32) -> Result<i64, IntcodeError> {
         if location < &0 {
-            Err("Accessing memory in negative index")?;
+            return Err(IntcodeError::NegativeAddress { addr: *location as i64, ip: self.instruction_pointer });
         }
-        Ok(*self.memory.get(&location).unwrap_or(&0))
+        Ok(self.memory.get(*location as usize))
     }
 
-    pub fn write_memory(&mut self, location: i32, value: i64, mode: ParamMode) -> Result<(), Box<dyn Error>> {
+    pub fn write_memory(&mut self, location: i32, value: i64, mode: ParamMode) -> Result<(), IntcodeError> {
         let location = match mode {
-            ParamMode::ImmediateMode => panic!("wrong param mode for write"),
+            ParamMode::ImmediateMode => return Err(IntcodeError::ImmediateWrite { ip: self.instruction_pointer }),
             ParamMode::PositionMode => location,
-            ParamMode::RelativeMode => location + self.relative_base,
+            ParamMode::RelativeMode => self.address(location as i64 + self.relative_base as i64)?,
         };
         if location < 0 {
-            Err("Writing memory in negative index")?;
+            return Err(IntcodeError::NegativeAddress { addr: location as i64, ip: self.instruction_pointer });
         }
-        self.memory.insert(location, value);
+        self.memory.set(location as usize, value);
+        if let Some(entry) = self.decode_cache.as_mut().and_then(|cache| cache.get_mut(location as usize)) {
+            *entry = None;
+        }
+        self.log_access(MemoryAccess::Write { address: location, value });
         Ok(())
     }
 
-    fn load_param(&self, location: &i32, mode: ParamMode) -> Result<i64, Box<dyn Error>> {
-        match mode {
-            ParamMode::ImmediateMode => Ok(self.read_memory(location)?),
-            ParamMode::PositionMode => {
-                let absolute_position = self.read_memory(location)?;
-                Ok(self.read_memory(&(absolute_position as i32))?)
-            },
-            ParamMode::RelativeMode => {
-                let relative_position = self.read_memory(location)? as i32;
-                Ok(self.read_memory(&(relative_position + self.relative_base))?)
-            }
+    fn load_param(&mut self, location: &i32, mode: ParamMode) -> Result<i64, IntcodeError> {
+        let address = match mode {
+            ParamMode::ImmediateMode => return self.read_memory(location),
+            ParamMode::PositionMode => self.address(self.read_memory(location)?)?,
+            ParamMode::RelativeMode => self.address(self.read_memory(location)?.saturating_add(self.relative_base as i64))?,
+        };
+        let value = self.read_memory(&address)?;
+        self.log_access(MemoryAccess::Read { address, value });
+        Ok(value)
+    }
+
+    /// Addresses and the relative base are i32, larger values are an error instead of being truncated
+    fn address(&self, value: i64) -> Result<i32, IntcodeError> {
+        i32::try_from(value).map_err(|_| IntcodeError::AddressOutOfRange { addr: value, ip: self.instruction_pointer })
+    }
+
+    /// Address `offset` words past the instruction pointer
+    fn param_location(&self, offset: i32) -> Result<i32, IntcodeError> {
+        self.address(self.instruction_pointer as i64 + offset as i64)
+    }
+
+    fn advance(&mut self, length: i32) -> Result<(), IntcodeError> {
+        self.instruction_pointer = self.param_location(length)?;
+        Ok(())
+    }
+
+    fn arithmetic(
+        &self,
+        a: i64,
+        b: i64,
+        checked: fn(i64, i64) -> Option<i64>,
+        wrapping: fn(i64, i64) -> i64,
+        saturating: fn(i64, i64) -> i64,
+    ) -> Result<i64, IntcodeError> {
+        match self.overflow_mode {
+            OverflowMode::Error => checked(a, b).ok_or(IntcodeError::Overflow { ip: self.instruction_pointer }),
+            OverflowMode::Wrap => Ok(wrapping(a, b)),
+            OverflowMode::Saturate => Ok(saturating(a, b)),
         }
     }
 
     pub fn dump_memory(&self) -> Vec<i64> {
-        let max = self.memory.keys().max().unwrap_or(&0) + 1;
-        let mut memory_vec = Vec::with_capacity(max as usize);
-        for key in 0..max {
-            memory_vec.push(self.read_memory(&key).unwrap());
-        }
-        memory_vec
+        self.memory.to_vec()
     }
 
     pub fn get_output(&self) -> Vec<i64> {
@@ -112,125 +403,276 @@ impl IntcodeComputer {
         output
     }
 
-    pub fn run_ignore_output(&mut self) -> Result<IntcodeComputerState, Box<dyn Error>> {
+    pub fn run_ignore_output(&mut self) -> Result<IntcodeComputerState, IntcodeError> {
         loop {
             match self.run()? {
-                IntcodeComputerState::Halted => return Ok(IntcodeComputerState::Halted),
-                IntcodeComputerState::WaitingForInput => return Ok(IntcodeComputerState::WaitingForInput),
                 IntcodeComputerState::OutputProduced(_) => (),
+                state => return Ok(state),
             }
         }
     }
 
-    pub fn run(&mut self) -> Result<IntcodeComputerState, Box<dyn Error>> {
+    pub fn run_until_halt(&mut self) -> Result<Vec<i64>, IntcodeError> {
         loop {
-            // let op = self.memory.get(&self.instruction_pointer)
-            let op = &self.read_memory(&self.instruction_pointer)?;
-            match get_op_code(op) {
-                1 => {
-                    // addition
-                    let a = self.load_param(&(self.instruction_pointer + 1), get_param_mode(op, 0))?;
-                    let b = self.load_param(&(self.instruction_pointer + 2), get_param_mode(op, 1))?;
-                    let output_location = self.load_param(&(self.instruction_pointer + 3), ParamMode::ImmediateMode)?;
-                    self.write_memory(output_location as i32, a + b, get_param_mode(op, 2))?;
-                    self.instruction_pointer += 4;
-                },
-                2 => {
-                    // multiplication
-                    let a = self.load_param(&(self.instruction_pointer + 1), get_param_mode(op, 0))?;
-                    let b = self.load_param(&(self.instruction_pointer + 2), get_param_mode(op, 1))?;
-                    let output_location = self.load_param(&(self.instruction_pointer + 3), ParamMode::ImmediateMode)?;
-                    self.write_memory(output_location as i32, a * b, get_param_mode(op, 2))?;
-                    self.instruction_pointer += 4;
-                },
-                3 => {
-                    // input
-                    let output_location = self.load_param(&(self.instruction_pointer + 1), ParamMode::ImmediateMode)? as i32;
-                    if let Some(value) = self.input_queue.pop_front() {
-                        self.write_memory(output_location, value, get_param_mode(op, 0))?;
-                        self.instruction_pointer += 2;
-                    } else {
-                        return Ok(IntcodeComputerState::WaitingForInput);
-                    }
-                },
-                4 => {
-                    // output
-                    let output = self.load_param(&(self.instruction_pointer + 1), get_param_mode(op, 0))?;
-                    self.instruction_pointer += 2;
-                    self.output.push(output);
-                    self.output_queue.push_back(output);
-                    return Ok(IntcodeComputerState::OutputProduced(output));
-                },
-                5 => {
-                    // jump if true
-                    let input = self.load_param(&(self.instruction_pointer + 1), get_param_mode(op, 0))?;
-                    let target = self.load_param(&(self.instruction_pointer + 2), get_param_mode(op, 1))?;
-                    if input != 0 {
-                        self.instruction_pointer = target as i32;
-                    } else {
-                        self.instruction_pointer += 3;
-                    }
-                },
-                6 => {
-                    // jump if false
-                    let input = self.load_param(&(self.instruction_pointer + 1), get_param_mode(op, 0))?;
-                    let target = self.load_param(&(self.instruction_pointer + 2), get_param_mode(op, 1))?;
-                    if input == 0 {
-                        self.instruction_pointer = target as i32;
-                    } else {
-                        self.instruction_pointer += 3;
-                    }
-                },
-                7 => {
-                    // less than
-                    let a = self.load_param(&(self.instruction_pointer + 1), get_param_mode(op, 0))?;
-                    let b = self.load_param(&(self.instruction_pointer + 2), get_param_mode(op, 1))?;
-                    let output_location = self.load_param(&(self.instruction_pointer + 3), ParamMode::ImmediateMode)?;
-                    self.write_memory(output_location as i32, if a < b { 1 } else { 0 }, get_param_mode(op, 2))?;
-                    self.instruction_pointer += 4;
-                },
-                8 => {
-                    // equals
-                    let a = self.load_param(&(self.instruction_pointer + 1), get_param_mode(op, 0))?;
-                    let b = self.load_param(&(self.instruction_pointer + 2), get_param_mode(op, 1))?;
-                    let output_location = self.load_param(&(self.instruction_pointer + 3), ParamMode::ImmediateMode)?;
-                    self.write_memory(output_location as i32, if a == b { 1 } else { 0 }, get_param_mode(op, 2))?;
-                    self.instruction_pointer += 4;
-                },
-                9 => {
-                    // shift relative base
-                    let a = self.load_param(&(self.instruction_pointer + 1), get_param_mode(op, 0))? as i32;
-                    self.relative_base += a;
-                    self.instruction_pointer += 2;
+            match self.run()? {
+                IntcodeComputerState::Halted => return Ok(self.pop_output()),
+                IntcodeComputerState::OutputProduced(_) => (),
+                _ => return Err(IntcodeError::InputExhausted),
+            }
+        }
+    }
+
+    fn param_mode(&self, modes: &Modes, param_index: u32) -> Result<ParamMode, IntcodeError> {
+        let op = match modes {
+            Modes::Decoded(modes) => return Ok(modes[param_index as usize]),
+            Modes::Encoded(op) => op,
+        };
+        get_param_mode(op, param_index).ok_or_else(|| IntcodeError::InvalidParamMode {
+            mode: op / (10_i64.pow(param_index + 2)) % 10,
+            ip: self.instruction_pointer,
+        })
+    }
+
+    /// Opcode and modes at the instruction pointer. Words that don't decode aren't cached so
+    /// errors are still reported where the interpreter runs into them.
+    fn fetch(&mut self) -> Result<(i64, Modes), IntcodeError> {
+        let address = self.instruction_pointer as usize;
+        if let Some(cache) = &self.decode_cache {
+            if let Some(Some(decoded)) = cache.get(address) {
+                return Ok((decoded.code, Modes::Decoded(decoded.modes)));
+            }
+        }
+        let op = self.read_memory(&self.instruction_pointer)?;
+        if let Some(cache) = &mut self.decode_cache {
+            if let Some(decoded) = Decoded::new(op).filter(|_| address < DECODE_CACHE_LIMIT) {
+                if cache.len() <= address {
+                    cache.resize(address + 1, None);
                 }
-                99 => {
-                    return Ok(IntcodeComputerState::Halted);
+                cache[address] = Some(decoded);
+                return Ok((decoded.code, Modes::Decoded(decoded.modes)));
+            }
+        }
+        Ok((get_op_code(&op), Modes::Encoded(op)))
+    }
+
+    pub fn run(&mut self) -> Result<IntcodeComputerState, IntcodeError> {
+        loop {
+            if let Some(state) = self.step()? {
+                return Ok(state);
+            }
+        }
+    }
+
+    /// Runs until halt, reading inputs from and writing outputs to `io` instead of the queues.
+    /// Queued inputs are used first, `Blocked` is returned once `io` has no more input.
+    pub fn run_with_io<T: IntcodeIo>(&mut self, mut io: T) -> Result<IntcodeComputerState, IntcodeError> {
+        loop {
+            match self.run()? {
+                IntcodeComputerState::WaitingForInput => match io.read()? {
+                    Some(value) => self.provide_input(value),
+                    None => return Ok(IntcodeComputerState::Blocked),
                 },
-                _ => Err(format!("Unsupported operation {} at {}", get_op_code(op), self.instruction_pointer))?,
+                IntcodeComputerState::OutputProduced(value) => {
+                    self.output_queue.pop_back();
+                    io.write(value)?;
+                }
+                state => return Ok(state),
+            }
+        }
+    }
+
+    /// Like `run` but stops with `BudgetExhausted` after executing `budget` instructions
+    pub fn run_with_budget(&mut self, budget: u64) -> Result<IntcodeComputerState, IntcodeError> {
+        let limit = self.instructions_executed.saturating_add(budget);
+        while self.instructions_executed < limit {
+            if let Some(state) = self.step()? {
+                return Ok(state);
+            }
+        }
+        Ok(IntcodeComputerState::BudgetExhausted)
+    }
+
+    /// Executes a single instruction, returns a state only where `run` would stop
+    pub fn step(&mut self) -> Result<Option<IntcodeComputerState>, IntcodeError> {
+        if self.tracer.is_none() {
+            return self.execute();
+        }
+        let index = self.instructions_executed;
+        let instruction_pointer = self.instruction_pointer;
+        let relative_base_before = self.relative_base;
+        let op = self.read_memory(&instruction_pointer)?;
+        let length = Opcode::from_code(get_op_code(&op)).map_or(1, |opcode| opcode.param_count() + 1);
+        let words = (0..length as i32)
+            .map(|offset| self.read_memory(&self.param_location(offset)?))
+            .collect::<Result<Vec<i64>, IntcodeError>>()?;
+        if let Some(tracer) = &mut self.tracer {
+            tracer.accesses.clear();
+        }
+        let state = self.execute()?;
+        if state == Some(IntcodeComputerState::WaitingForInput) {
+            return Ok(state);
+        }
+        if let Some(tracer) = &mut self.tracer {
+            let step = TraceStep {
+                index,
+                instruction_pointer,
+                words,
+                accesses: std::mem::take(&mut tracer.accesses),
+                relative_base_before,
+                relative_base_after: self.relative_base,
+            };
+            tracer.sink.lock().unwrap().record(&step);
+        }
+        Ok(state)
+    }
+
+    fn execute(&mut self) -> Result<Option<IntcodeComputerState>, IntcodeError> {
+        if self.profile.is_none() {
+            return self.execute_instruction();
+        }
+        let address = self.instruction_pointer;
+        let op = self.read_memory(&address)?;
+        let state = self.execute_instruction()?;
+        // like `instructions_executed`, counts neither a blocked IN nor the halting HLT
+        if !matches!(state, Some(IntcodeComputerState::WaitingForInput) | Some(IntcodeComputerState::Halted)) {
+            if let (Some(profile), Some(opcode)) = (&mut self.profile, Opcode::from_code(get_op_code(&op))) {
+                profile.count_instruction(address as usize, opcode);
             }
         }
+        Ok(state)
+    }
+
+    fn execute_instruction(&mut self) -> Result<Option<IntcodeComputerState>, IntcodeError> {
+        let (code, modes) = self.fetch()?;
+        match code {
+            1 => {
+                // addition
+                let a = self.load_param(&self.param_location(1)?, self.param_mode(&modes, 0)?)?;
+                let b = self.load_param(&self.param_location(2)?, self.param_mode(&modes, 1)?)?;
+                let output_location = self.load_param(&self.param_location(3)?, ParamMode::ImmediateMode)?;
+                let sum = self.arithmetic(a, b, i64::checked_add, i64::wrapping_add, i64::saturating_add)?;
+                self.write_memory(self.address(output_location)?, sum, self.param_mode(&modes, 2)?)?;
+                self.advance(4)?;
+            },
+            2 => {
+                // multiplication
+                let a = self.load_param(&self.param_location(1)?, self.param_mode(&modes, 0)?)?;
+                let b = self.load_param(&self.param_location(2)?, self.param_mode(&modes, 1)?)?;
+                let output_location = self.load_param(&self.param_location(3)?, ParamMode::ImmediateMode)?;
+                let product = self.arithmetic(a, b, i64::checked_mul, i64::wrapping_mul, i64::saturating_mul)?;
+                self.write_memory(self.address(output_location)?, product, self.param_mode(&modes, 2)?)?;
+                self.advance(4)?;
+            },
+            3 => {
+                // input
+                let output_location = self.load_param(&self.param_location(1)?, ParamMode::ImmediateMode)?;
+                let output_location = self.address(output_location)?;
+                if let Some(value) = self.input_queue.pop_front() {
+                    self.write_memory(output_location, value, self.param_mode(&modes, 0)?)?;
+                    self.advance(2)?;
+                } else {
+                    return Ok(Some(IntcodeComputerState::WaitingForInput));
+                }
+            },
+            4 => {
+                // output
+                let output = self.load_param(&self.param_location(1)?, self.param_mode(&modes, 0)?)?;
+                self.advance(2)?;
+                self.output.push(output);
+                self.output_queue.push_back(output);
+                self.instructions_executed += 1;
+                return Ok(Some(IntcodeComputerState::OutputProduced(output)));
+            },
+            5 => {
+                // jump if true
+                let input = self.load_param(&self.param_location(1)?, self.param_mode(&modes, 0)?)?;
+                let target = self.load_param(&self.param_location(2)?, self.param_mode(&modes, 1)?)?;
+                if input != 0 {
+                    self.instruction_pointer = self.address(target)?;
+                } else {
+                    self.advance(3)?;
+                }
+            },
+            6 => {
+                // jump if false
+                let input = self.load_param(&self.param_location(1)?, self.param_mode(&modes, 0)?)?;
+                let target = self.load_param(&self.param_location(2)?, self.param_mode(&modes, 1)?)?;
+                if input == 0 {
+                    self.instruction_pointer = self.address(target)?;
+                } else {
+                    self.advance(3)?;
+                }
+            },
+            7 => {
+                // less than
+                let a = self.load_param(&self.param_location(1)?, self.param_mode(&modes, 0)?)?;
+                let b = self.load_param(&self.param_location(2)?, self.param_mode(&modes, 1)?)?;
+                let output_location = self.load_param(&self.param_location(3)?, ParamMode::ImmediateMode)?;
+                self.write_memory(self.address(output_location)?, if a < b { 1 } else { 0 }, self.param_mode(&modes, 2)?)?;
+                self.advance(4)?;
+            },
+            8 => {
+                // equals
+                let a = self.load_param(&self.param_location(1)?, self.param_mode(&modes, 0)?)?;
+                let b = self.load_param(&self.param_location(2)?, self.param_mode(&modes, 1)?)?;
+                let output_location = self.load_param(&self.param_location(3)?, ParamMode::ImmediateMode)?;
+                self.write_memory(self.address(output_location)?, if a == b { 1 } else { 0 }, self.param_mode(&modes, 2)?)?;
+                self.advance(4)?;
+            },
+            9 => {
+                // shift relative base
+                let a = self.load_param(&self.param_location(1)?, self.param_mode(&modes, 0)?)?;
+                self.relative_base = self.address(a.saturating_add(self.relative_base as i64))?;
+                self.advance(2)?;
+            }
+            99 => {
+                return Ok(Some(IntcodeComputerState::Halted));
+            },
+            _ => {
+                let op = self.read_memory(&self.instruction_pointer)?;
+                return Err(IntcodeError::UnknownOpcode { op, ip: self.instruction_pointer });
+            }
+        }
+        self.instructions_executed += 1;
+        Ok(None)
+    }
+}
+
+pub fn load_input(path: &str) -> Result<Vec<i64>, IntcodeError> {
+    Ok(parse_program(&read_program(path)?))
+}
+
+/// The file's text, `InputNotAvailable` if there is nothing to read
+pub(crate) fn read_program(path: &str) -> Result<String, IntcodeError> {
+    let not_available = || IntcodeError::InputNotAvailable { path: path.to_owned() };
+    match fs::read_to_string(path) {
+        Ok(text) if text.trim().is_empty() => Err(not_available()),
+        Ok(text) => Ok(text),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Err(not_available()),
+        Err(e) => Err(IntcodeError::Io {
+            path: path.to_owned(),
+            message: e.to_string(),
+        }),
     }
 }
 
-fn load_input(path: &str) -> Vec<i64> {
-    fs::read_to_string(path)
-        .expect("Something went wrong reading the file")
-        .split(",")
-        .filter_map(|s| s.parse::<i64>().ok())
+pub fn parse_program(text: &str) -> Vec<i64> {
+    text.split(",")
+        .filter_map(|s| s.trim().parse::<i64>().ok())
         .collect()
 }
 
-fn get_op_code(op: &i64) -> i64 {
+pub fn get_op_code(op: &i64) -> i64 {
     op % 100
 }
 
-fn get_param_mode(op: &i64, param_index: u32) -> ParamMode {
+pub fn get_param_mode(op: &i64, param_index: u32) -> Option<ParamMode> {
     let mode = op / (10_i64.pow(param_index + 2)) % 10;
     match mode {
-        0 => ParamMode::PositionMode,
-        1 => ParamMode::ImmediateMode,
-        2 => ParamMode::RelativeMode,
-        _ => panic!("Unknown param mode"),
+        0 => Some(ParamMode::PositionMode),
+        1 => Some(ParamMode::ImmediateMode),
+        2 => Some(ParamMode::RelativeMode),
+        _ => None,
     }
 }
 
@@ -247,9 +689,9 @@ mod tests {
 
     #[test]
     fn get_param_mode_one() {
-        assert_eq!(get_param_mode(&1002, 0), ParamMode::PositionMode);
-        assert_eq!(get_param_mode(&1002, 1), ParamMode::ImmediateMode);
-        assert_eq!(get_param_mode(&1002, 2), ParamMode::PositionMode);
+        assert_eq!(get_param_mode(&1002, 0), Some(ParamMode::PositionMode));
+        assert_eq!(get_param_mode(&1002, 1), Some(ParamMode::ImmediateMode));
+        assert_eq!(get_param_mode(&1002, 2), Some(ParamMode::PositionMode));
     }
 
     #[test]
@@ -270,7 +712,7 @@ mod tests {
 
     #[test]
     fn test_computer_input() {
-        let program = load_input("input/day_five.txt");
+        let program = load_input("input/day_five.txt").unwrap();
         let mut computer = IntcodeComputer::new(program);
         computer.provide_input(1);
         computer.run_ignore_output().unwrap();
@@ -287,6 +729,7 @@ mod tests {
                 IntcodeComputerState::Halted => break,
                 IntcodeComputerState::OutputProduced(res) => output = res,
                 IntcodeComputerState::WaitingForInput => computer.provide_input(6),
+                IntcodeComputerState::BudgetExhausted | IntcodeComputerState::Blocked => unreachable!(),
             }
         }
         assert_eq!(output, 1);
@@ -301,7 +744,8 @@ mod tests {
             match computer.run().unwrap() {
                 IntcodeComputerState::Halted => break,
                 IntcodeComputerState::OutputProduced(res) => output = res,
-                IntcodeComputerState::WaitingForInput => computer.provide_input(0), 
+                IntcodeComputerState::WaitingForInput => computer.provide_input(0),
+                IntcodeComputerState::BudgetExhausted | IntcodeComputerState::Blocked => unreachable!(),
             }
         }
         assert_eq!(output, 0);
@@ -317,6 +761,7 @@ mod tests {
                 IntcodeComputerState::Halted => break,
                 IntcodeComputerState::OutputProduced(res) => output = res,
                 IntcodeComputerState::WaitingForInput => computer.provide_input(6),
+                IntcodeComputerState::BudgetExhausted | IntcodeComputerState::Blocked => unreachable!(),
             }
         }
         assert_eq!(output, 1);
@@ -332,6 +777,7 @@ mod tests {
                 IntcodeComputerState::Halted => break,
                 IntcodeComputerState::OutputProduced(res) => output = res,
                 IntcodeComputerState::WaitingForInput => computer.provide_input(0),
+                IntcodeComputerState::BudgetExhausted | IntcodeComputerState::Blocked => unreachable!(),
             }
         }
         assert_eq!(output, 0);
@@ -346,7 +792,8 @@ mod tests {
             match computer.run().unwrap() {
                 IntcodeComputerState::Halted => break,
                 IntcodeComputerState::OutputProduced(res) => output = res,
-                IntcodeComputerState::WaitingForInput => computer.provide_input(input.pop().unwrap())
+                IntcodeComputerState::WaitingForInput => computer.provide_input(input.pop().unwrap()),
+                IntcodeComputerState::BudgetExhausted | IntcodeComputerState::Blocked => unreachable!(),
             }
         }
         vec![output]
@@ -448,9 +895,200 @@ mod tests {
 
     #[test]
     fn day_5_task_2() {
-        let mut program = load_input("input/day_five.txt");
+        let mut program = load_input("input/day_five.txt").unwrap();
         let input = vec![5];
         let output = test_wrapper(&mut program, Some(input));
         assert_eq!(output, vec![3629692]);
     }
+
+    #[test]
+    fn unknown_opcode_is_reported() {
+        let mut computer = IntcodeComputer::new(vec![1101, 1, 1, 5, 42, 0]);
+        let err = computer.run().unwrap_err();
+        assert_eq!(err, IntcodeError::UnknownOpcode { op: 42, ip: 4 });
+    }
+
+    #[test]
+    fn invalid_param_mode_is_reported() {
+        let mut computer = IntcodeComputer::new(vec![104, 0, 304, 0, 99]);
+        computer.run().unwrap();
+        let err = computer.run().unwrap_err();
+        assert_eq!(err, IntcodeError::InvalidParamMode { mode: 3, ip: 2 });
+    }
+
+    #[test]
+    fn negative_address_is_reported() {
+        let mut computer = IntcodeComputer::new(vec![4, -7, 99]);
+        let err = computer.run().unwrap_err();
+        assert_eq!(err, IntcodeError::NegativeAddress { addr: -7, ip: 0 });
+    }
+
+    #[test]
+    fn immediate_write_is_reported() {
+        let mut computer = IntcodeComputer::new(vec![11101, 1, 1, 5, 99]);
+        let err = computer.run().unwrap_err();
+        assert_eq!(err, IntcodeError::ImmediateWrite { ip: 0 });
+        let err = computer.write_memory(0, 1, ParamMode::ImmediateMode).unwrap_err();
+        assert_eq!(err, IntcodeError::ImmediateWrite { ip: 0 });
+    }
+
+    #[test]
+    fn input_exhausted_is_reported() {
+        let mut computer = IntcodeComputer::new(vec![3, 0, 99]);
+        assert_eq!(computer.run_until_halt().unwrap_err(), IntcodeError::InputExhausted);
+    }
+
+    #[test]
+    fn missing_file_is_reported() {
+        match load_input("input/does_not_exist.txt").unwrap_err() {
+            IntcodeError::InputNotAvailable { path } => assert_eq!(path, "input/does_not_exist.txt"),
+            other => panic!("unexpected error {:?}", other),
+        }
+        let empty = std::env::temp_dir().join(format!("intcode_empty_{}.txt", std::process::id()));
+        fs::write(&empty, "\n").unwrap();
+        let path = empty.to_string_lossy().into_owned();
+        assert_eq!(load_input(&path).unwrap_err(), IntcodeError::InputNotAvailable { path: path.clone() });
+        fs::remove_file(empty).unwrap();
+    }
+
+    #[test]
+    fn restore_rewinds_to_snapshot() {
+        let program = vec![3, 100, 109, 7, 1001, 100, 1, 100, 4, 100, 99];
+        let mut computer = IntcodeComputer::new(program);
+        computer.provide_input_iter(vec![5, 6]);
+        let snapshot = computer.snapshot();
+        assert_eq!(computer.run().unwrap(), IntcodeComputerState::OutputProduced(6));
+        assert_eq!(computer.relative_base(), 7);
+
+        computer.restore(&snapshot);
+        assert_eq!(computer.instruction_pointer(), 0);
+        assert_eq!(computer.relative_base(), 0);
+        assert_eq!(computer.input_queue(), &VecDeque::from(vec![5, 6]));
+        assert!(computer.output_queue().is_empty());
+        assert_eq!(computer.peek_memory(100).unwrap(), 0);
+        assert_eq!(computer.run_until_halt().unwrap(), vec![6]);
+    }
+
+    #[test]
+    fn snapshot_is_not_affected_by_later_writes() {
+        let mut computer = IntcodeComputer::new(vec![1101, 2, 3, 0, 99]);
+        let snapshot = computer.snapshot();
+        let mut branch = computer.clone();
+        computer.run().unwrap();
+        branch.write_memory(1, 10, ParamMode::PositionMode).unwrap();
+        branch.run().unwrap();
+        assert_eq!(computer.dump_memory(), vec![5, 2, 3, 0, 99]);
+        assert_eq!(branch.dump_memory(), vec![13, 10, 3, 0, 99]);
+        computer.restore(&snapshot);
+        assert_eq!(computer.dump_memory(), vec![1101, 2, 3, 0, 99]);
+    }
+
+    #[test]
+    fn budget_stops_endless_loop() {
+        // JNZ #1, #0
+        let mut computer = IntcodeComputer::new(vec![1105, 1, 0]);
+        assert_eq!(computer.run_with_budget(100).unwrap(), IntcodeComputerState::BudgetExhausted);
+        assert_eq!(computer.instructions_executed(), 100);
+        assert_eq!(computer.run_with_budget(0).unwrap(), IntcodeComputerState::BudgetExhausted);
+        assert_eq!(computer.instructions_executed(), 100);
+    }
+
+    #[test]
+    fn budgeted_run_resumes() {
+        let mut unlimited = IntcodeComputer::new_from_file("input/day_nine.txt").unwrap();
+        unlimited.provide_input(1);
+        let expected = unlimited.run_until_halt().unwrap();
+
+        let mut computer = IntcodeComputer::new_from_file("input/day_nine.txt").unwrap();
+        computer.provide_input(1);
+        let mut pauses = 0;
+        loop {
+            match computer.run_with_budget(7).unwrap() {
+                IntcodeComputerState::BudgetExhausted => pauses += 1,
+                IntcodeComputerState::OutputProduced(_) => (),
+                IntcodeComputerState::Halted => break,
+                IntcodeComputerState::WaitingForInput | IntcodeComputerState::Blocked => panic!("unexpected input request"),
+            }
+        }
+        assert!(pauses > 0);
+        assert_eq!(computer.pop_output(), expected);
+        assert_eq!(computer.instructions_executed(), unlimited.instructions_executed());
+    }
+
+    fn run_both_ways(program: &[i64], inputs: &[i64]) -> (IntcodeComputer, IntcodeComputer) {
+        let mut computers = vec![];
+        for cached in &[false, true] {
+            let mut computer = IntcodeComputer::new(program.to_vec());
+            computer.set_decode_cache(*cached);
+            computer.provide_input_iter(inputs.iter().cloned());
+            computer.run_until_halt().unwrap();
+            computers.push(computer);
+        }
+        let cached = computers.pop().unwrap();
+        (computers.pop().unwrap(), cached)
+    }
+
+    #[test]
+    fn decode_cache_matches_interpreter() {
+        let day_five = load_input("input/day_five.txt").unwrap();
+        let day_nine = load_input("input/day_nine.txt").unwrap();
+        let day_nineteen = load_input("input/day_nineteen.txt").unwrap();
+        let runs = vec![(&day_five, vec![1]), (&day_five, vec![5]), (&day_nine, vec![1]), (&day_nineteen, vec![12, 9])];
+        for (program, inputs) in runs {
+            let (interpreted, cached) = run_both_ways(program, &inputs);
+            assert_eq!(interpreted.get_output(), cached.get_output());
+            assert_eq!(interpreted.instructions_executed(), cached.instructions_executed());
+            assert_eq!(interpreted.dump_memory(), cached.dump_memory());
+        }
+    }
+
+    #[test]
+    fn decode_cache_sees_overwritten_code() {
+        // OUT #7, ADD #0, #99 -> [0], JNZ #1, #0
+        let mut computer = IntcodeComputer::new(vec![104, 7, 1101, 0, 99, 0, 1105, 1, 0]);
+        computer.set_decode_cache(true);
+        assert_eq!(computer.run_with_budget(10).unwrap(), IntcodeComputerState::OutputProduced(7));
+        assert_eq!(computer.run_with_budget(10).unwrap(), IntcodeComputerState::Halted);
+
+        let mut computer = IntcodeComputer::new(vec![104, 0, 304, 0, 99]);
+        computer.set_decode_cache(true);
+        computer.run().unwrap();
+        assert_eq!(computer.run().unwrap_err(), IntcodeError::InvalidParamMode { mode: 3, ip: 2 });
+    }
+
+    #[test]
+    fn overflow_modes() {
+        // MUL [9], #2 -> [9], ADD [9], #1 -> [9], HLT
+        let program = vec![1002, 9, 2, 9, 1001, 9, 1, 9, 99, i64::MAX];
+        let mut computer = IntcodeComputer::new(program.clone());
+        assert_eq!(computer.run().unwrap_err(), IntcodeError::Overflow { ip: 0 });
+        let expected = vec![(OverflowMode::Wrap, -1), (OverflowMode::Saturate, i64::MAX)];
+        for (mode, value) in expected {
+            let mut computer = IntcodeComputer::new(program.clone());
+            computer.set_overflow_mode(mode);
+            assert_eq!(computer.run().unwrap(), IntcodeComputerState::Halted);
+            assert_eq!(computer.peek_memory(9).unwrap(), value);
+        }
+    }
+
+    #[test]
+    fn address_truncation_is_reported() {
+        let far = 1_i64 << 40;
+        let mut computer = IntcodeComputer::new(vec![1105, 1, far]);
+        assert_eq!(computer.run().unwrap_err(), IntcodeError::AddressOutOfRange { addr: far, ip: 0 });
+        let mut computer = IntcodeComputer::new(vec![4, far]);
+        assert_eq!(computer.run().unwrap_err(), IntcodeError::AddressOutOfRange { addr: far, ip: 0 });
+        let mut computer = IntcodeComputer::new(vec![109, i32::MAX as i64, 109, 1, 99]);
+        let err = computer.run().unwrap_err();
+        assert_eq!(err, IntcodeError::AddressOutOfRange { addr: i32::MAX as i64 + 1, ip: 2 });
+
+        // an output as the last addressable instruction can't move the instruction pointer on
+        let last = i32::MAX as i64 - 1;
+        let mut computer = IntcodeComputer::new(vec![1101, 4, 0, last, 1105, 1, last]);
+        let err = computer.run().unwrap_err();
+        assert_eq!(err, IntcodeError::AddressOutOfRange { addr: i32::MAX as i64 + 1, ip: last as i32 });
+        let mut computer = IntcodeComputer::new(vec![1101, 1, 0, i32::MAX as i64, 1105, 1, i32::MAX as i64]);
+        let err = computer.run().unwrap_err();
+        assert_eq!(err, IntcodeError::AddressOutOfRange { addr: i32::MAX as i64 + 1, ip: i32::MAX });
+    }
 }
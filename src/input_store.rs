@@ -0,0 +1,276 @@
+use crate::checksum::fnv1a;
+use crate::puzzle_input::{file_name, InputSource, INPUT_DIR_VAR};
+use crate::solution::SolveError;
+use std::env;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+#[cfg(feature = "download")]
+use std::time::Duration;
+
+/// Session cookie of the account whose inputs get downloaded
+pub const SESSION_VAR: &str = "AOC_SESSION";
+
+const DEFAULT_CACHE_DIR: &str = "input/downloads";
+#[cfg(feature = "download")]
+const BASE_URL: &str = "https://adventofcode.com";
+#[cfg(feature = "download")]
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Somewhere a day's puzzle input can be looked up
+pub trait InputStore {
+    /// `NoInput` when the store doesn't have the day
+    fn input(&self, day: u32) -> Result<String, SolveError>;
+}
+
+impl InputStore for InputSource {
+    fn input(&self, day: u32) -> Result<String, SolveError> {
+        self.read(day)
+    }
+}
+
+/// Downloads inputs over HTTPS with the account's session cookie
+#[cfg(feature = "download")]
+pub struct HttpFetcher {
+    base_url: String,
+    session: String,
+    year: u32,
+}
+
+#[cfg(feature = "download")]
+impl HttpFetcher {
+    pub fn new(session: &str) -> HttpFetcher {
+        HttpFetcher::with_base_url(BASE_URL, session)
+    }
+
+    fn with_base_url(base_url: &str, session: &str) -> HttpFetcher {
+        HttpFetcher {
+            base_url: base_url.to_owned(),
+            session: session.to_owned(),
+            year: 2019,
+        }
+    }
+}
+
+#[cfg(feature = "download")]
+impl InputStore for HttpFetcher {
+    fn input(&self, day: u32) -> Result<String, SolveError> {
+        let url = format!("{}/{}/day/{}/input", self.base_url, self.year, day);
+        let error = |message: String| SolveError::Io {
+            path: url.clone(),
+            message,
+        };
+        let response = ureq::get(&url)
+            .set("Cookie", &format!("session={}", self.session))
+            .set("User-Agent", "advent_of_code_2019 input fetcher")
+            .timeout(TIMEOUT)
+            .call();
+        match response {
+            Ok(response) => response.into_string().map_err(|e| error(e.to_string())),
+            Err(ureq::Error::Status(404, _)) => Err(SolveError::NoInput(day)),
+            Err(ureq::Error::Status(code, response)) => {
+                Err(error(format!("server answered {} {}", code, response.status_text())))
+            }
+            Err(e) => Err(error(e.to_string())),
+        }
+    }
+}
+
+/// Inputs kept in a directory, each next to a `.fnv1a` checksum file. Missing days are fetched
+/// from `upstream` and stored along with their checksum. Files that can't be read, have no
+/// checksum or don't match it are reported rather than used or replaced.
+pub struct CachedStore {
+    dir: PathBuf,
+    upstream: Option<Box<dyn InputStore>>,
+}
+
+fn io_error(path: &Path, message: String) -> SolveError {
+    SolveError::Io {
+        path: path.to_string_lossy().into_owned(),
+        message,
+    }
+}
+
+impl CachedStore {
+    pub fn new<P: Into<PathBuf>>(dir: P, upstream: Option<Box<dyn InputStore>>) -> CachedStore {
+        CachedStore { dir: dir.into(), upstream }
+    }
+
+    fn paths(&self, day: u32) -> Result<(PathBuf, PathBuf), SolveError> {
+        let name = file_name(day).ok_or(SolveError::NoInput(day))?;
+        Ok((self.dir.join(&name), self.dir.join(format!("{}.fnv1a", name))))
+    }
+
+    /// The cached input, `None` if it was never stored
+    fn cached(&self, day: u32) -> Result<Option<String>, SolveError> {
+        let (input_path, checksum_path) = self.paths(day)?;
+        let input = match fs::read_to_string(&input_path) {
+            Ok(input) => input,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(io_error(&input_path, e.to_string())),
+        };
+        let stored = fs::read_to_string(&checksum_path).map_err(|e| io_error(&checksum_path, e.to_string()))?;
+        let checksum = format!("{:016x}", fnv1a(input.as_bytes()));
+        if stored.trim() != checksum {
+            return Err(io_error(
+                &input_path,
+                format!("checksum {} doesn't match the stored {}", checksum, stored.trim()),
+            ));
+        }
+        Ok(Some(input))
+    }
+
+    fn write(&self, path: &Path, contents: &str) -> Result<(), SolveError> {
+        fs::write(path, contents).map_err(|e| io_error(path, e.to_string()))
+    }
+
+    pub fn store(&self, day: u32, input: &str) -> Result<(), SolveError> {
+        let (input_path, checksum_path) = self.paths(day)?;
+        fs::create_dir_all(&self.dir).map_err(|e| io_error(&self.dir, e.to_string()))?;
+        self.write(&input_path, input)?;
+        self.write(&checksum_path, &format!("{:016x}\n", fnv1a(input.as_bytes())))
+    }
+}
+
+impl InputStore for CachedStore {
+    fn input(&self, day: u32) -> Result<String, SolveError> {
+        if let Some(input) = self.cached(day)? {
+            return Ok(input);
+        }
+        let upstream = self.upstream.as_ref().ok_or(SolveError::NoInput(day))?;
+        let input = upstream.input(day)?;
+        if input.trim().is_empty() {
+            return Err(SolveError::NoInput(day));
+        }
+        self.store(day, &input)?;
+        Ok(input)
+    }
+}
+
+/// Downloads into `AOC_INPUT_DIR`, or `input/downloads`, when `AOC_SESSION` is set and reads
+/// `InputSource::from_env` otherwise. Downloading needs the `download` feature.
+pub fn from_env() -> Result<Box<dyn InputStore>, String> {
+    match env::var(SESSION_VAR) {
+        Ok(session) => {
+            let dir = env::var(INPUT_DIR_VAR).unwrap_or_else(|_| DEFAULT_CACHE_DIR.to_owned());
+            downloading_store(dir, &session)
+        }
+        Err(_) => Ok(Box::new(InputSource::from_env())),
+    }
+}
+
+#[cfg(feature = "download")]
+fn downloading_store(dir: String, session: &str) -> Result<Box<dyn InputStore>, String> {
+    Ok(Box::new(CachedStore::new(dir, Some(Box::new(HttpFetcher::new(session))))))
+}
+
+#[cfg(not(feature = "download"))]
+fn downloading_store(_dir: String, _session: &str) -> Result<Box<dyn InputStore>, String> {
+    Err(format!("{} is set but aoc was built without the download feature", SESSION_VAR))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    /// Hands out the same input and counts how often it was asked
+    struct Upstream {
+        input: &'static str,
+        requests: Cell<usize>,
+    }
+
+    impl InputStore for Upstream {
+        fn input(&self, _day: u32) -> Result<String, SolveError> {
+            self.requests.set(self.requests.get() + 1);
+            Ok(self.input.to_owned())
+        }
+    }
+
+    fn cache_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("aoc_input_store_{}_{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn fetches_once_then_reads_the_cache() {
+        let dir = cache_dir("fetch");
+        let upstream = Box::new(Upstream { input: "1,2,3\n", requests: Cell::new(0) });
+        let store = CachedStore::new(&dir, Some(upstream));
+        assert_eq!(store.input(5).unwrap(), "1,2,3\n");
+        assert_eq!(store.input(5).unwrap(), "1,2,3\n");
+        assert_eq!(fs::read_to_string(dir.join("day_five.txt.fnv1a")).unwrap(), format!("{:016x}\n", fnv1a(b"1,2,3\n")));
+
+        let offline = CachedStore::new(&dir, None);
+        assert_eq!(offline.input(5).unwrap(), "1,2,3\n");
+        assert_eq!(offline.input(6), Err(SolveError::NoInput(6)));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn damaged_cache_is_reported() {
+        let dir = cache_dir("damaged");
+        let store = CachedStore::new(&dir, Some(Box::new(Upstream { input: "1,2,3\n", requests: Cell::new(0) })));
+        store.input(5).unwrap();
+        fs::write(dir.join("day_five.txt"), "1,2,4\n").unwrap();
+        match store.input(5) {
+            Err(SolveError::Io { message, .. }) => assert!(message.contains("doesn't match")),
+            result => panic!("expected a checksum error, got {:?}", result),
+        }
+
+        // files put there by hand have no checksum to trust
+        fs::write(dir.join("day_six.txt"), "A)B\n").unwrap();
+        match CachedStore::new(&dir, None).input(6) {
+            Err(SolveError::Io { path, .. }) => assert!(path.ends_with("day_six.txt.fnv1a")),
+            result => panic!("expected an io error, got {:?}", result),
+        }
+        assert!(!dir.join("day_six.txt.fnv1a").exists());
+
+        fs::create_dir(dir.join("day_seven.txt")).unwrap();
+        assert!(matches!(store.input(7), Err(SolveError::Io { .. })));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(feature = "download")]
+    #[test]
+    fn downloads_with_the_session_cookie() {
+        use std::io::{Read, Write};
+        use std::net::TcpListener;
+        use std::thread;
+
+        let stub_server = |response: &'static str| {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let base_url = format!("http://{}", listener.local_addr().unwrap());
+            let server = thread::spawn(move || {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut request = vec![];
+                let mut buffer = [0; 1024];
+                while !String::from_utf8_lossy(&request).contains("\r\n\r\n") {
+                    let read = stream.read(&mut buffer).unwrap();
+                    request.extend_from_slice(&buffer[..read]);
+                }
+                stream.write_all(response.as_bytes()).unwrap();
+                String::from_utf8(request).unwrap()
+            });
+            (base_url, server)
+        };
+
+        let (base_url, server) = stub_server("HTTP/1.1 200 OK\r\nContent-Length: 6\r\n\r\n1,2,3\n");
+        assert_eq!(HttpFetcher::with_base_url(&base_url, "secret").input(5).unwrap(), "1,2,3\n");
+        let request = server.join().unwrap();
+        assert!(request.starts_with("GET /2019/day/5/input HTTP/1.1\r\n"));
+        assert!(request.to_lowercase().contains("\r\ncookie: session=secret\r\n"));
+
+        let (base_url, server) = stub_server("HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n");
+        assert_eq!(HttpFetcher::with_base_url(&base_url, "secret").input(25), Err(SolveError::NoInput(25)));
+        server.join().unwrap();
+
+        let (base_url, server) = stub_server("HTTP/1.1 400 Bad Request\r\nContent-Length: 15\r\n\r\nexpired session");
+        match HttpFetcher::with_base_url(&base_url, "expired").input(1) {
+            Err(SolveError::Io { message, .. }) => assert_eq!(message, "server answered 400 Bad Request"),
+            result => panic!("expected an io error, got {:?}", result),
+        }
+        server.join().unwrap();
+    }
+}
@@ -0,0 +1,188 @@
+use crate::infinite_memory_intcomputer::{IntcodeComputer, IntcodeComputerState, IntcodeError};
+use std::error::Error;
+use std::io::{self, BufRead, Write};
+
+/// Text interface for Intcode programs that talk ASCII.
+///
+/// Outputs outside 0..=255 aren't text, they are collected as non-ASCII results instead.
+pub struct AsciiComputer {
+    computer: IntcodeComputer,
+    non_ascii: Vec<i64>,
+    halted: bool,
+}
+
+impl AsciiComputer {
+    pub fn new(computer: IntcodeComputer) -> AsciiComputer {
+        AsciiComputer {
+            computer,
+            non_ascii: vec![],
+            halted: false,
+        }
+    }
+
+    pub fn computer(&self) -> &IntcodeComputer {
+        &self.computer
+    }
+
+    pub fn into_computer(self) -> IntcodeComputer {
+        self.computer
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.halted
+    }
+
+    /// Queues the line followed by a newline
+    pub fn send_line(&mut self, line: &str) -> Result<(), IntcodeError> {
+        if !line.is_ascii() {
+            return Err(IntcodeError::InvalidInput { text: line.to_owned() });
+        }
+        self.computer.provide_input_iter(line.bytes().chain(Some(b'\n')).map(i64::from));
+        Ok(())
+    }
+
+    /// Runs until the program printed a newline or stopped, the newline isn't returned.
+    /// `None` once the program stopped without printing anything.
+    pub fn read_line(&mut self) -> Result<Option<String>, IntcodeError> {
+        let mut line = String::new();
+        while let Some(c) = self.next_char()? {
+            if c == '\n' {
+                return Ok(Some(line));
+            }
+            line.push(c);
+        }
+        Ok(if line.is_empty() { None } else { Some(line) })
+    }
+
+    /// Runs until the program waits for input or halts and returns everything it printed
+    pub fn read_until_prompt(&mut self) -> Result<String, IntcodeError> {
+        let mut text = String::new();
+        while let Some(c) = self.next_char()? {
+            text.push(c);
+        }
+        Ok(text)
+    }
+
+    /// Values printed so far that weren't ASCII, like a final puzzle answer
+    pub fn take_non_ascii(&mut self) -> Vec<i64> {
+        std::mem::take(&mut self.non_ascii)
+    }
+
+    fn next_char(&mut self) -> Result<Option<char>, IntcodeError> {
+        while let Some(value) = self.next_value()? {
+            if (0..=255).contains(&value) {
+                return Ok(Some(value as u8 as char));
+            }
+            self.non_ascii.push(value);
+        }
+        Ok(None)
+    }
+
+    fn next_value(&mut self) -> Result<Option<i64>, IntcodeError> {
+        match self.computer.run()? {
+            IntcodeComputerState::OutputProduced(value) => {
+                self.computer.pop_output();
+                Ok(Some(value))
+            }
+            IntcodeComputerState::Halted => {
+                self.halted = true;
+                Ok(None)
+            }
+            IntcodeComputerState::WaitingForInput => Ok(None),
+            state @ (IntcodeComputerState::BudgetExhausted | IntcodeComputerState::Blocked) => {
+                Err(IntcodeError::UnexpectedState(state))
+            }
+        }
+    }
+
+    /// Plays the program as a text adventure, reading commands from `input` until it halts
+    pub fn interactive<R: BufRead, W: Write>(&mut self, mut input: R, mut output: W) -> Result<(), Box<dyn Error>> {
+        loop {
+            while let Some(value) = self.next_value()? {
+                if (0..=255).contains(&value) {
+                    write!(output, "{}", value as u8 as char)?;
+                } else {
+                    writeln!(output, "[non-ASCII result: {}]", value)?;
+                }
+            }
+            output.flush()?;
+            if self.halted {
+                return Ok(());
+            }
+            let mut line = String::new();
+            if input.read_line(&mut line)? == 0 {
+                return Ok(());
+            }
+            self.send_line(line.trim_end_matches(&['\r', '\n'][..]))?;
+        }
+    }
+}
+
+/// Runs an ASCII Intcode program on the terminal
+pub fn play_file(path: &str) -> Result<(), Box<dyn Error>> {
+    let mut computer = AsciiComputer::new(IntcodeComputer::new_from_file(path)?);
+    let stdin = io::stdin();
+    computer.interactive(stdin.lock(), io::stdout())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode_assembler::assemble;
+
+    // prompts with "> ", echoes each line followed by the result 99999, says bye on an empty line
+    fn echo() -> AsciiComputer {
+        let source = "
+            prompt:
+                OUT #62
+                OUT #32
+                IN -> [c]
+                EQ [c], #10 -> [t]
+                JNZ [t], #quit
+            echo:
+                OUT [c]
+                IN -> [c]
+                EQ [c], #10 -> [t]
+                JZ [t], #echo
+                OUT #10
+                OUT #99999
+                JNZ #1, #prompt
+            quit:
+                OUT #98
+                OUT #121
+                OUT #101
+                HLT
+            c: DATA 0
+            t: DATA 0
+        ";
+        AsciiComputer::new(IntcodeComputer::new(assemble(source).unwrap()))
+    }
+
+    #[test]
+    fn lines_and_prompts() {
+        let mut computer = echo();
+        assert_eq!(computer.read_until_prompt().unwrap(), "> ");
+        computer.send_line("hi there").unwrap();
+        assert_eq!(computer.read_line().unwrap(), Some("hi there".to_owned()));
+        assert_eq!(computer.read_until_prompt().unwrap(), "> ");
+        assert_eq!(computer.take_non_ascii(), vec![99999]);
+        computer.send_line("").unwrap();
+        assert_eq!(computer.read_line().unwrap(), Some("bye".to_owned()));
+        assert!(computer.is_halted());
+        assert_eq!(computer.read_line().unwrap(), None);
+        assert_eq!(
+            computer.send_line("é"),
+            Err(IntcodeError::InvalidInput { text: "é".to_owned() })
+        );
+    }
+
+    #[test]
+    fn interactive_session() {
+        let mut transcript = vec![];
+        echo().interactive(&b"one\ntwo\r\n\n"[..], &mut transcript).unwrap();
+        assert_eq!(
+            String::from_utf8(transcript).unwrap(),
+            "> one\n[non-ASCII result: 99999]\n> two\n[non-ASCII result: 99999]\n> bye"
+        );
+    }
+}
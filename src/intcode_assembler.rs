@@ -0,0 +1,354 @@
+use crate::infinite_memory_intcomputer::ParamMode;
+use crate::intcode_disassembler::{mode_digit, Opcode};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+
+/// Most cells a single `ZERO` may reserve
+const MAX_ZERO_CELLS: usize = 1 << 20;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AssemblerError {
+    UnknownMnemonic { line: usize, mnemonic: String },
+    InvalidOperand { line: usize, operand: String },
+    WrongOperandCount { line: usize, expected: usize, found: usize },
+    MissingDestination { line: usize },
+    UnexpectedDestination { line: usize },
+    ImmediateWrite { line: usize },
+    InvalidLabel { line: usize, label: String },
+    DuplicateLabel { line: usize, label: String },
+    UnknownLabel { line: usize, label: String },
+    ReservationTooLarge { line: usize, count: usize },
+}
+
+impl fmt::Display for AssemblerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AssemblerError::UnknownMnemonic { line, mnemonic } => {
+                write!(f, "line {}: unknown mnemonic {}", line, mnemonic)
+            }
+            AssemblerError::InvalidOperand { line, operand } => {
+                write!(f, "line {}: invalid operand {}", line, operand)
+            }
+            AssemblerError::WrongOperandCount { line, expected, found } => {
+                write!(f, "line {}: expected {} operands, found {}", line, expected, found)
+            }
+            AssemblerError::MissingDestination { line } => write!(f, "line {}: missing -> destination", line),
+            AssemblerError::UnexpectedDestination { line } => {
+                write!(f, "line {}: instruction doesn't write to memory", line)
+            }
+            AssemblerError::ImmediateWrite { line } => write!(f, "line {}: can't write to immediate operand", line),
+            AssemblerError::InvalidLabel { line, label } => write!(f, "line {}: invalid label {}", line, label),
+            AssemblerError::DuplicateLabel { line, label } => write!(f, "line {}: label {} defined twice", line, label),
+            AssemblerError::UnknownLabel { line, label } => write!(f, "line {}: unknown label {}", line, label),
+            AssemblerError::ReservationTooLarge { line, count } => {
+                write!(f, "line {}: ZERO {} is more than {} cells", line, count, MAX_ZERO_CELLS)
+            }
+        }
+    }
+}
+
+impl Error for AssemblerError {}
+
+#[derive(Debug, Clone)]
+enum Value {
+    Number(i64),
+    Label(String),
+}
+
+#[derive(Debug, Clone)]
+struct Word {
+    line: usize,
+    value: Value,
+}
+
+fn is_identifier(text: &str) -> bool {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) if first.is_ascii_alphabetic() || first == '_' => {
+            chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        }
+        _ => false,
+    }
+}
+
+fn parse_value(text: &str, line: usize) -> Result<Value, AssemblerError> {
+    let text = text.trim();
+    if let Ok(number) = text.parse::<i64>() {
+        Ok(Value::Number(number))
+    } else if is_identifier(text) {
+        Ok(Value::Label(text.to_owned()))
+    } else {
+        Err(AssemblerError::InvalidOperand {
+            line,
+            operand: text.to_owned(),
+        })
+    }
+}
+
+fn parse_operand(text: &str, line: usize) -> Result<(ParamMode, Value), AssemblerError> {
+    let text = text.trim();
+    let invalid = || AssemblerError::InvalidOperand {
+        line,
+        operand: text.to_owned(),
+    };
+    if let Some(value) = text.strip_prefix('#') {
+        return Ok((ParamMode::ImmediateMode, parse_value(value, line)?));
+    }
+    let inner = text
+        .strip_prefix('[')
+        .and_then(|rest| rest.strip_suffix(']'))
+        .ok_or_else(invalid)?
+        .trim();
+    let is_relative = inner.get(..2).is_some_and(|prefix| prefix.eq_ignore_ascii_case("rb"));
+    let offset = if is_relative {
+        let rest = inner[2..].trim();
+        if rest.is_empty() || rest.starts_with('+') || rest.starts_with('-') {
+            Some(rest)
+        } else {
+            None
+        }
+    } else {
+        None
+    };
+    match offset {
+        Some("") => Ok((ParamMode::RelativeMode, Value::Number(0))),
+        Some(offset) => {
+            let (sign, magnitude) = offset.split_at(1);
+            let magnitude = magnitude.trim();
+            // u64 parsing takes a leading `+`, so check for a second sign here
+            if !magnitude.starts_with(|c: char| c.is_ascii_digit()) {
+                return Err(invalid());
+            }
+            let magnitude = i128::from(magnitude.parse::<u64>().map_err(|_| invalid())?);
+            let offset = if sign == "-" { -magnitude } else { magnitude };
+            Ok((ParamMode::RelativeMode, Value::Number(i64::try_from(offset).map_err(|_| invalid())?)))
+        }
+        None => Ok((ParamMode::PositionMode, parse_value(inner, line).map_err(|_| invalid())?)),
+    }
+}
+
+fn split_list(text: &str) -> Vec<&str> {
+    if text.trim().is_empty() {
+        vec![]
+    } else {
+        text.split(',').collect()
+    }
+}
+
+fn parse_instruction(opcode: Opcode, rest: &str, line: usize) -> Result<Vec<Word>, AssemblerError> {
+    let mut parts = rest.splitn(2, "->");
+    let sources = split_list(parts.next().unwrap_or(""));
+    let destination = parts.next();
+    let mut operand_texts = sources;
+    match (opcode.writes_last_param(), destination) {
+        (true, Some(destination)) => operand_texts.push(destination),
+        (true, None) => return Err(AssemblerError::MissingDestination { line }),
+        (false, Some(_)) => return Err(AssemblerError::UnexpectedDestination { line }),
+        (false, None) => (),
+    }
+    if operand_texts.len() != opcode.param_count() {
+        return Err(AssemblerError::WrongOperandCount {
+            line,
+            expected: opcode.param_count(),
+            found: operand_texts.len(),
+        });
+    }
+    let mut op = opcode.code();
+    let mut words = vec![];
+    for (i, text) in operand_texts.iter().enumerate() {
+        let (mode, value) = parse_operand(text, line)?;
+        if mode == ParamMode::ImmediateMode && opcode.writes_last_param() && i + 1 == opcode.param_count() {
+            return Err(AssemblerError::ImmediateWrite { line });
+        }
+        op += mode_digit(mode) * 10_i64.pow(i as u32 + 2);
+        words.push(Word { line, value });
+    }
+    words.insert(
+        0,
+        Word {
+            line,
+            value: Value::Number(op),
+        },
+    );
+    Ok(words)
+}
+
+/// Assembles a program in the same syntax the disassembler prints.
+///
+/// Operands are `#5` immediate, `[5]` position and `[rb+5]` relative, written destinations
+/// follow `->`. Labels are defined with `name:` and can be used wherever a number can.
+/// `DATA a, b, ...` emits raw values and `ZERO n` reserves `n` zeroed cells.
+pub fn assemble(source: &str) -> Result<Vec<i64>, AssemblerError> {
+    let mut words: Vec<Word> = vec![];
+    let mut labels: HashMap<String, usize> = HashMap::new();
+    for (index, text) in source.lines().enumerate() {
+        let line = index + 1;
+        let mut text = text.split(';').next().unwrap_or("").trim();
+        while let Some(colon) = text.find(':') {
+            let label = text[..colon].trim();
+            if !is_identifier(label) {
+                return Err(AssemblerError::InvalidLabel {
+                    line,
+                    label: label.to_owned(),
+                });
+            }
+            if labels.insert(label.to_owned(), words.len()).is_some() {
+                return Err(AssemblerError::DuplicateLabel {
+                    line,
+                    label: label.to_owned(),
+                });
+            }
+            text = text[colon + 1..].trim();
+        }
+        if text.is_empty() {
+            continue;
+        }
+        let (mnemonic, rest) = match text.find(char::is_whitespace) {
+            Some(split) => (&text[..split], &text[split..]),
+            None => (text, ""),
+        };
+        if mnemonic.eq_ignore_ascii_case("DATA") {
+            for value in split_list(rest) {
+                words.push(Word {
+                    line,
+                    value: parse_value(value, line)?,
+                });
+            }
+        } else if mnemonic.eq_ignore_ascii_case("ZERO") {
+            let count = rest.trim().parse::<usize>().map_err(|_| AssemblerError::InvalidOperand {
+                line,
+                operand: rest.trim().to_owned(),
+            })?;
+            if count > MAX_ZERO_CELLS {
+                return Err(AssemblerError::ReservationTooLarge { line, count });
+            }
+            for _ in 0..count {
+                words.push(Word {
+                    line,
+                    value: Value::Number(0),
+                });
+            }
+        } else {
+            let opcode = Opcode::from_mnemonic(mnemonic).ok_or_else(|| AssemblerError::UnknownMnemonic {
+                line,
+                mnemonic: mnemonic.to_owned(),
+            })?;
+            words.extend(parse_instruction(opcode, rest, line)?);
+        }
+    }
+
+    let mut program = Vec::with_capacity(words.len());
+    for word in words {
+        let value = match word.value {
+            Value::Number(value) => value,
+            Value::Label(label) => match labels.get(&label) {
+                Some(address) => *address as i64,
+                None => {
+                    return Err(AssemblerError::UnknownLabel {
+                        line: word.line,
+                        label,
+                    })
+                }
+            },
+        };
+        program.push(value);
+    }
+    Ok(program)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::infinite_memory_intcomputer::{load_input, IntcodeComputer};
+    use crate::intcode_disassembler::disassemble;
+
+    #[test]
+    fn assembles_modes() {
+        let program = assemble("ADD [rb+3], #5 -> [100]\nMUL [4], #3 -> [4]\nOUT [rb-1]\nHLT").unwrap();
+        assert_eq!(program, vec![1201, 3, 5, 100, 1002, 4, 3, 4, 204, -1, 99]);
+    }
+
+    #[test]
+    fn runs_assembled_countdown() {
+        let source = "
+            ; prints counter down to 1
+            loop:
+                OUT [counter]
+                ADD [counter], #-1 -> [counter]
+                JNZ [counter], #loop
+                HLT
+            counter: DATA 3
+        ";
+        let mut computer = IntcodeComputer::new(assemble(source).unwrap());
+        assert_eq!(computer.run_until_halt().unwrap(), vec![3, 2, 1]);
+    }
+
+    #[test]
+    fn zero_directive_and_labels() {
+        let program = assemble("IN -> [rb_buffer]\nHLT\nrb_buffer: ZERO 2\nDATA rb_buffer, -4, [rb]").unwrap_err();
+        assert_eq!(program, AssemblerError::InvalidOperand { line: 4, operand: "[rb]".to_owned() });
+        let program = assemble("IN -> [rb_buffer]\nHLT\nrb_buffer: ZERO 2\nDATA rb_buffer, -4\nOUT [rb]").unwrap();
+        assert_eq!(program, vec![3, 3, 99, 0, 0, 3, -4, 204, 0]);
+    }
+
+    #[test]
+    fn reports_errors() {
+        assert_eq!(
+            assemble("NOP").unwrap_err(),
+            AssemblerError::UnknownMnemonic { line: 1, mnemonic: "NOP".to_owned() }
+        );
+        assert_eq!(assemble("\nADD #1, #2 -> #3").unwrap_err(), AssemblerError::ImmediateWrite { line: 2 });
+        assert_eq!(assemble("ADD #1, #2").unwrap_err(), AssemblerError::MissingDestination { line: 1 });
+        assert_eq!(
+            assemble("OUT #1, #2").unwrap_err(),
+            AssemblerError::WrongOperandCount { line: 1, expected: 1, found: 2 }
+        );
+        assert_eq!(
+            assemble("JNZ #1, #nowhere").unwrap_err(),
+            AssemblerError::UnknownLabel { line: 1, label: "nowhere".to_owned() }
+        );
+        assert_eq!(
+            assemble("a: HLT\na: HLT").unwrap_err(),
+            AssemblerError::DuplicateLabel { line: 2, label: "a".to_owned() }
+        );
+        assert_eq!(
+            assemble("OUT [rb*2]").unwrap_err(),
+            AssemblerError::InvalidOperand { line: 1, operand: "[rb*2]".to_owned() }
+        );
+        for operand in &["[rb--5]", "[rb+-5]", "[rb-+5]", "[rb++5]", "[rb+9223372036854775808]"] {
+            assert_eq!(
+                assemble(&format!("OUT {}", operand)).unwrap_err(),
+                AssemblerError::InvalidOperand { line: 1, operand: operand.to_string() }
+            );
+        }
+        assert_eq!(
+            assemble("ZERO 9999999999999").unwrap_err(),
+            AssemblerError::ReservationTooLarge { line: 1, count: 9999999999999 }
+        );
+    }
+
+    #[test]
+    fn round_trip_extreme_offsets() {
+        assert_eq!(assemble("OUT [rb - 5]").unwrap(), vec![204, -5]);
+        assert_round_trip(vec![204, i64::MIN, 204, i64::MAX, 99]);
+    }
+
+    fn assert_round_trip(program: Vec<i64>) {
+        let listing = disassemble(&program).to_string();
+        let assembled = assemble(&listing).unwrap();
+        assert_eq!(assembled, program);
+        assert_eq!(disassemble(&assembled).to_string(), listing);
+    }
+
+    #[test]
+    fn round_trip_day_5() {
+        assert_round_trip(load_input("input/day_five.txt").unwrap());
+    }
+
+    #[test]
+    fn round_trip_day_9() {
+        assert_round_trip(load_input("input/day_nine.txt").unwrap());
+    }
+}
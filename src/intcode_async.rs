@@ -0,0 +1,168 @@
+use crate::infinite_memory_intcomputer::{IntcodeComputer, IntcodeComputerState, IntcodeError};
+use futures::{Sink, Stream};
+use std::pin::Pin;
+use std::task::{Context, Poll, Waker};
+
+/// Instructions to run before giving the executor back, so a busy machine doesn't starve other tasks
+const INSTRUCTIONS_PER_POLL: u64 = 10_000;
+
+/// Runs an `IntcodeComputer` as a `Stream` of its outputs and a `Sink` for its inputs.
+///
+/// Waiting for input suspends the stream until something is sent. Once the sink is closed a
+/// machine that still wants input ends the stream with `InputExhausted`.
+/// `StreamExt::split` gives the two halves to separate tasks.
+pub struct AsyncComputer {
+    computer: IntcodeComputer,
+    input_waker: Option<Waker>,
+    inputs_closed: bool,
+    finished: bool,
+}
+
+impl AsyncComputer {
+    pub fn new(computer: IntcodeComputer) -> AsyncComputer {
+        AsyncComputer {
+            computer,
+            input_waker: None,
+            inputs_closed: false,
+            finished: false,
+        }
+    }
+
+    pub fn into_computer(self) -> IntcodeComputer {
+        self.computer
+    }
+
+    fn wake_reader(&mut self) {
+        if let Some(waker) = self.input_waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+impl Stream for AsyncComputer {
+    type Item = Result<i64, IntcodeError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        if this.finished {
+            return Poll::Ready(None);
+        }
+        let result = match this.computer.run_with_budget(INSTRUCTIONS_PER_POLL) {
+            Ok(IntcodeComputerState::OutputProduced(value)) => {
+                this.computer.pop_output();
+                Ok(value)
+            }
+            Ok(IntcodeComputerState::Halted) => {
+                this.finished = true;
+                return Poll::Ready(None);
+            }
+            Ok(IntcodeComputerState::WaitingForInput) if this.inputs_closed => Err(IntcodeError::InputExhausted),
+            Ok(IntcodeComputerState::WaitingForInput) => {
+                this.input_waker = Some(cx.waker().clone());
+                return Poll::Pending;
+            }
+            Ok(IntcodeComputerState::BudgetExhausted) => {
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+            Ok(state @ IntcodeComputerState::Blocked) => Err(IntcodeError::UnexpectedState(state)),
+            Err(error) => Err(error),
+        };
+        if result.is_err() {
+            this.finished = true;
+        }
+        Poll::Ready(Some(result))
+    }
+}
+
+/// Inputs are queued without limit, so the sink is always ready
+impl Sink<i64> for AsyncComputer {
+    type Error = IntcodeError;
+
+    fn poll_ready(self: Pin<&mut Self>, _: &mut Context) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn start_send(self: Pin<&mut Self>, input: i64) -> Result<(), Self::Error> {
+        let this = self.get_mut();
+        this.computer.provide_input(input);
+        this.wake_reader();
+        Ok(())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _: &mut Context) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _: &mut Context) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+        this.inputs_closed = true;
+        this.wake_reader();
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode_assembler::assemble;
+    use futures::executor::block_on;
+    use futures::{join, SinkExt, StreamExt};
+
+    // doubles every input until it reads a 0
+    fn doubler() -> AsyncComputer {
+        let source = "
+            loop:
+                IN -> [value]
+                JZ [value], #end
+                MUL [value], #2 -> [value]
+                OUT [value]
+                JNZ #1, #loop
+            end:
+                HLT
+            value: DATA 0
+        ";
+        AsyncComputer::new(IntcodeComputer::new(assemble(source).unwrap()))
+    }
+
+    #[test]
+    fn outputs_wait_for_inputs() {
+        let (mut inputs, outputs) = doubler().split();
+        let producer = async {
+            for value in &[1, 2, 3, 0] {
+                inputs.send(*value).await.unwrap();
+            }
+        };
+        let consumer = outputs.collect::<Vec<_>>();
+        let ((), outputs) = block_on(async { join!(producer, consumer) });
+        assert_eq!(outputs, vec![Ok(2), Ok(4), Ok(6)]);
+    }
+
+    #[test]
+    fn closed_inputs_end_the_stream() {
+        let mut computer = doubler();
+        block_on(async {
+            computer.send(21).await.unwrap();
+            assert_eq!(computer.next().await, Some(Ok(42)));
+            computer.close().await.unwrap();
+            assert_eq!(computer.next().await, Some(Err(IntcodeError::InputExhausted)));
+            assert_eq!(computer.next().await, None);
+        });
+    }
+
+    #[test]
+    fn long_runs_yield() {
+        // counts down from 50000 before printing
+        let source = "
+                ADD #50000, #0 -> [n]
+            loop:
+                ADD [n], #-1 -> [n]
+                JNZ [n], #loop
+                OUT #7
+                HLT
+            n: DATA 0
+        ";
+        let computer = AsyncComputer::new(IntcodeComputer::new(assemble(source).unwrap()));
+        assert_eq!(block_on(computer.collect::<Vec<_>>()), vec![Ok(7)]);
+    }
+}
@@ -0,0 +1,260 @@
+use crate::infinite_memory_intcomputer::{
+    get_op_code, load_input, IntcodeComputer, IntcodeComputerState, IntcodeError, MemoryAccess,
+};
+use crate::intcode_disassembler::Opcode;
+use crate::intcode_memory::Memory;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+const ITERATIONS: u32 = 5;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BenchmarkError {
+    Intcode(IntcodeError),
+    /// The decode cache made a workload produce different outputs
+    Mismatch { name: String },
+}
+
+impl fmt::Display for BenchmarkError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BenchmarkError::Intcode(error) => write!(f, "{}", error),
+            BenchmarkError::Mismatch { name } => write!(f, "{}: decode cache changed the outputs", name),
+        }
+    }
+}
+
+impl Error for BenchmarkError {}
+
+impl From<IntcodeError> for BenchmarkError {
+    fn from(error: IntcodeError) -> BenchmarkError {
+        BenchmarkError::Intcode(error)
+    }
+}
+
+trait Backend: Clone {
+    fn read(&self, address: usize) -> i64;
+    fn write(&mut self, address: usize, value: i64);
+}
+
+impl Backend for Memory {
+    fn read(&self, address: usize) -> i64 {
+        self.get(address)
+    }
+
+    fn write(&mut self, address: usize, value: i64) {
+        self.set(address, value)
+    }
+}
+
+/// The memory layout IntcodeComputer used before paging
+#[derive(Clone)]
+struct HashMapMemory(HashMap<i32, i64>);
+
+impl HashMapMemory {
+    fn new(program: &[i64]) -> HashMapMemory {
+        HashMapMemory(program.iter().enumerate().map(|(i, v)| (i as i32, *v)).collect())
+    }
+}
+
+impl Backend for HashMapMemory {
+    fn read(&self, address: usize) -> i64 {
+        *self.0.get(&(address as i32)).unwrap_or(&0)
+    }
+
+    fn write(&mut self, address: usize, value: i64) {
+        self.0.insert(address as i32, value);
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Access {
+    Read(usize),
+    Write(usize, i64),
+}
+
+/// Runs the program and records every memory access, instruction fetches included
+fn record(program: &[i64], inputs: &[i64]) -> Result<Vec<Access>, IntcodeError> {
+    let mut computer = IntcodeComputer::new(program.to_vec());
+    computer.record_accesses(true);
+    computer.provide_input_iter(inputs.iter().cloned());
+    let mut trace = vec![];
+    loop {
+        let ip = computer.instruction_pointer();
+        let op = computer.peek_memory(ip)?;
+        let length = Opcode::from_code(get_op_code(&op)).map_or(1, |opcode| opcode.param_count() + 1);
+        trace.extend((ip as usize..ip as usize + length).map(Access::Read));
+        let state = computer.step()?;
+        trace.extend(computer.take_accesses().into_iter().map(|access| match access {
+            MemoryAccess::Read { address, .. } => Access::Read(address as usize),
+            MemoryAccess::Write { address, value } => Access::Write(address as usize, value),
+        }));
+        match state {
+            Some(IntcodeComputerState::Halted) | Some(IntcodeComputerState::WaitingForInput) => return Ok(trace),
+            _ => (),
+        }
+    }
+}
+
+fn replay<B: Backend>(base: &B, trace: &[Access]) -> i64 {
+    let mut memory = base.clone();
+    let mut checksum = 0_i64;
+    for access in trace {
+        match access {
+            Access::Read(address) => checksum = checksum.wrapping_add(memory.read(*address)),
+            Access::Write(address, value) => memory.write(*address, *value),
+        }
+    }
+    checksum
+}
+
+fn time<F: FnMut() -> Result<i64, IntcodeError>>(mut work: F) -> Result<Duration, IntcodeError> {
+    let mut best = Duration::from_secs(u64::MAX);
+    for _ in 0..ITERATIONS {
+        let start = Instant::now();
+        std::hint::black_box(work()?);
+        best = best.min(start.elapsed());
+    }
+    Ok(best)
+}
+
+pub struct BenchmarkResult {
+    pub name: String,
+    pub hash_map: Duration,
+    pub paged: Duration,
+}
+
+impl BenchmarkResult {
+    pub fn speedup(&self) -> f64 {
+        self.hash_map.as_secs_f64() / self.paged.as_secs_f64()
+    }
+}
+
+/// Replays the memory traffic of one probe per traces entry on a fresh clone of the program,
+/// the way day 19 clones its computer for every position it checks
+fn compare_backends(name: &str, program: &[i64], traces: &[Vec<Access>]) -> Result<BenchmarkResult, IntcodeError> {
+    let hash_map = HashMapMemory::new(program);
+    let paged = Memory::new(program);
+    Ok(BenchmarkResult {
+        name: name.to_owned(),
+        hash_map: time(|| Ok(traces.iter().map(|trace| replay(&hash_map, trace)).sum()))?,
+        paged: time(|| Ok(traces.iter().map(|trace| replay(&paged, trace)).sum()))?,
+    })
+}
+
+pub fn memory_benchmarks() -> Result<Vec<BenchmarkResult>, IntcodeError> {
+    let mut results = vec![];
+
+    let boost = load_input("input/day_nine.txt")?;
+    let trace = record(&boost, &[2])?;
+    results.push(compare_backends("day 9 BOOST sensor mode", &boost, &[trace])?);
+
+    let tractor = load_input("input/day_nineteen.txt")?;
+    let mut traces = vec![];
+    for y in 0..20 {
+        for x in 0..20 {
+            traces.push(record(&tractor, &[x, y])?);
+        }
+    }
+    results.push(compare_backends("day 19 20x20 probes", &tractor, &traces)?);
+    Ok(results)
+}
+
+pub struct DecodeBenchmark {
+    pub name: String,
+    pub interpreted: Duration,
+    pub cached: Duration,
+}
+
+impl DecodeBenchmark {
+    pub fn speedup(&self) -> f64 {
+        self.interpreted.as_secs_f64() / self.cached.as_secs_f64()
+    }
+}
+
+/// Runs a clone of `base` per input set and sums the outputs
+fn run_all(base: &IntcodeComputer, inputs: &[Vec<i64>]) -> Result<i64, IntcodeError> {
+    let mut sum = 0;
+    for input in inputs {
+        let mut computer = base.clone();
+        computer.provide_input_iter(input.iter().cloned());
+        sum += computer.run_until_halt()?.iter().sum::<i64>();
+    }
+    Ok(sum)
+}
+
+fn compare_decoding(name: &str, program: &[i64], inputs: &[Vec<i64>]) -> Result<DecodeBenchmark, BenchmarkError> {
+    let interpreted = IntcodeComputer::new(program.to_vec());
+    let mut cached = interpreted.clone();
+    cached.set_decode_cache(true);
+    if run_all(&interpreted, inputs)? != run_all(&cached, inputs)? {
+        return Err(BenchmarkError::Mismatch { name: name.to_owned() });
+    }
+    Ok(DecodeBenchmark {
+        name: name.to_owned(),
+        interpreted: time(|| run_all(&interpreted, inputs))?,
+        cached: time(|| run_all(&cached, inputs))?,
+    })
+}
+
+pub fn decode_benchmarks() -> Result<Vec<DecodeBenchmark>, BenchmarkError> {
+    let boost = load_input("input/day_nine.txt")?;
+    let tractor = load_input("input/day_nineteen.txt")?;
+    let probes: Vec<Vec<i64>> = (0..20).flat_map(|y| (0..20).map(move |x| vec![x, y])).collect();
+    Ok(vec![
+        compare_decoding("day 9 BOOST sensor mode", &boost, &[vec![2]])?,
+        compare_decoding("day 19 20x20 probes", &tractor, &probes)?,
+    ])
+}
+
+pub fn run() -> Result<(), BenchmarkError> {
+    println!("{:<28} {:>12} {:>12} {:>8}", "memory workload", "hashmap", "paged", "speedup");
+    for result in memory_benchmarks()? {
+        println!(
+            "{:<28} {:>12?} {:>12?} {:>7.1}x",
+            result.name,
+            result.hash_map,
+            result.paged,
+            result.speedup()
+        );
+    }
+    println!();
+    println!("{:<28} {:>12} {:>12} {:>8}", "decode workload", "interpreted", "cached", "speedup");
+    for result in decode_benchmarks()? {
+        println!(
+            "{:<28} {:>12?} {:>12?} {:>7.1}x",
+            result.name,
+            result.interpreted,
+            result.cached,
+            result.speedup()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backends_agree() {
+        let program = load_input("input/day_nineteen.txt").unwrap();
+        let trace = record(&program, &[10, 12]).unwrap();
+        assert_eq!(replay(&HashMapMemory::new(&program), &trace), replay(&Memory::new(&program), &trace));
+    }
+
+    #[test]
+    fn decoding_errors_are_returned() {
+        let error = compare_decoding("needs input", &[3, 0, 99], &[vec![]]).err();
+        assert_eq!(error, Some(BenchmarkError::Intcode(IntcodeError::InputExhausted)));
+    }
+
+    // cargo test --release memory_benchmark -- --ignored --nocapture, runs the decode benchmarks too
+    #[test]
+    #[ignore]
+    fn memory_benchmark() {
+        run().unwrap();
+    }
+}
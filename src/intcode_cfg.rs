@@ -0,0 +1,287 @@
+use crate::infinite_memory_intcomputer::{load_input, IntcodeError, MemoryAccess, ParamMode};
+use crate::intcode_disassembler::{find_code, Instruction, Opcode};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BasicBlock {
+    pub start: usize,
+    pub instructions: Vec<Instruction>,
+    /// Start addresses of the blocks execution can continue with
+    pub successors: Vec<usize>,
+}
+
+impl BasicBlock {
+    /// First address after the block
+    pub fn end(&self) -> usize {
+        self.instructions.last().map_or(self.start, |last| last.address + last.len())
+    }
+
+    fn last(&self) -> &Instruction {
+        self.instructions.last().unwrap()
+    }
+
+    /// Whether the block ends in a jump whose target is only known at runtime
+    pub fn has_computed_exit(&self) -> bool {
+        self.last().is_computed_jump()
+    }
+}
+
+/// Control-flow graph of the code `find_code` reaches from the entry point
+#[derive(Debug, Clone)]
+pub struct ControlFlowGraph {
+    pub blocks: BTreeMap<usize, BasicBlock>,
+    /// Instructions whose words are overwritten, either by a position-mode write seen
+    /// statically or by a write passed to `mark_runtime_writes`
+    pub self_modified: BTreeSet<usize>,
+    /// Instructions that write into code
+    pub code_writers: BTreeSet<usize>,
+}
+
+impl ControlFlowGraph {
+    pub fn build(memory: &[i64]) -> ControlFlowGraph {
+        let (code, entry_points) = find_code(memory);
+        let mut leaders: BTreeSet<usize> = entry_points;
+        leaders.insert(0);
+        for instruction in code.values() {
+            if let Some(target) = instruction.static_jump_target() {
+                if target >= 0 {
+                    leaders.insert(target as usize);
+                }
+            }
+            if let Opcode::Jnz | Opcode::Jz | Opcode::Hlt = instruction.opcode {
+                leaders.insert(instruction.address + instruction.len());
+            }
+        }
+
+        let mut blocks: BTreeMap<usize, BasicBlock> = BTreeMap::new();
+        let mut current: Option<BasicBlock> = None;
+        for (address, instruction) in &code {
+            let continues = match &current {
+                Some(block) => block.end() == *address && !leaders.contains(address),
+                None => false,
+            };
+            if !continues {
+                if let Some(block) = current.take() {
+                    blocks.insert(block.start, block);
+                }
+                current = Some(BasicBlock {
+                    start: *address,
+                    instructions: vec![],
+                    successors: vec![],
+                });
+            }
+            current.as_mut().unwrap().instructions.push(instruction.clone());
+        }
+        if let Some(block) = current {
+            blocks.insert(block.start, block);
+        }
+
+        let starts: BTreeSet<usize> = blocks.keys().cloned().collect();
+        for block in blocks.values_mut() {
+            let last = block.last().clone();
+            if last.falls_through() && starts.contains(&block.end()) {
+                block.successors.push(block.end());
+            }
+            if let Some(target) = last.static_jump_target() {
+                if target >= 0 && starts.contains(&(target as usize)) && !block.successors.contains(&(target as usize)) {
+                    block.successors.push(target as usize);
+                }
+            }
+        }
+
+        let mut cfg = ControlFlowGraph {
+            blocks,
+            self_modified: BTreeSet::new(),
+            code_writers: BTreeSet::new(),
+        };
+        // relative writes depend on the base at runtime, only position-mode ones are resolved here
+        let writes: Vec<(usize, i64)> = code
+            .values()
+            .filter(|instruction| instruction.opcode.writes_last_param())
+            .filter_map(|instruction| {
+                let target = instruction.operands.last().unwrap();
+                match target.mode {
+                    ParamMode::PositionMode => Some((instruction.address, target.value)),
+                    _ => None,
+                }
+            })
+            .collect();
+        for (writer, target) in writes {
+            if cfg.mark_write(target) {
+                cfg.code_writers.insert(writer);
+            }
+        }
+        cfg
+    }
+
+    /// Instruction covering `address`, if it is code
+    pub fn instruction_at(&self, address: usize) -> Option<&Instruction> {
+        let (_, block) = self.blocks.range(..=address).next_back()?;
+        block
+            .instructions
+            .iter()
+            .find(|instruction| (instruction.address..instruction.address + instruction.len()).contains(&address))
+    }
+
+    fn mark_write(&mut self, address: i64) -> bool {
+        if address < 0 {
+            return false;
+        }
+        match self.instruction_at(address as usize).map(|instruction| instruction.address) {
+            Some(instruction) => {
+                self.self_modified.insert(instruction);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Marks the code hit by writes recorded with `IntcodeComputer::record_accesses`
+    pub fn mark_runtime_writes(&mut self, accesses: &[MemoryAccess]) {
+        for access in accesses {
+            if let MemoryAccess::Write { address, .. } = access {
+                self.mark_write(*address as i64);
+            }
+        }
+    }
+
+    pub fn computed_jumps(&self) -> Vec<usize> {
+        self.blocks
+            .values()
+            .filter(|block| block.has_computed_exit())
+            .map(|block| block.last().address)
+            .collect()
+    }
+
+    /// Addresses of the `IN` instructions, where the program reads its inputs
+    pub fn input_reads(&self) -> Vec<usize> {
+        self.blocks
+            .values()
+            .flat_map(|block| &block.instructions)
+            .filter(|instruction| instruction.opcode == Opcode::In)
+            .map(|instruction| instruction.address)
+            .collect()
+    }
+
+    /// Graphviz rendering. Computed jumps point at a `?` node, self-modified blocks are
+    /// filled orange and blocks that read input are drawn bold.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::new();
+        writeln!(dot, "digraph intcode {{").unwrap();
+        writeln!(dot, "    node [shape=box, fontname=\"monospace\"];").unwrap();
+        for block in self.blocks.values() {
+            let mut label = String::new();
+            for instruction in &block.instructions {
+                let mut text = format!("{}: {}", instruction.address, instruction);
+                if self.self_modified.contains(&instruction.address) {
+                    text.push_str(" (modified)");
+                }
+                if self.code_writers.contains(&instruction.address) {
+                    text.push_str(" (writes code)");
+                }
+                label.push_str(&text.replace('\\', "\\\\").replace('"', "\\\""));
+                label.push_str("\\l");
+            }
+            let mut attributes = vec![format!("label=\"{}\"", label)];
+            let modified = block
+                .instructions
+                .iter()
+                .any(|instruction| self.self_modified.contains(&instruction.address));
+            if modified {
+                attributes.push("style=filled, fillcolor=orange".to_owned());
+            }
+            if block.instructions.iter().any(|instruction| instruction.opcode == Opcode::In) {
+                attributes.push("penwidth=3".to_owned());
+            }
+            writeln!(dot, "    b{} [{}];", block.start, attributes.join(", ")).unwrap();
+            for successor in &block.successors {
+                writeln!(dot, "    b{} -> b{};", block.start, successor).unwrap();
+            }
+            if block.has_computed_exit() {
+                writeln!(dot, "    b{} -> computed [style=dashed, color=red];", block.start).unwrap();
+            }
+        }
+        if self.blocks.values().any(|block| block.has_computed_exit()) {
+            writeln!(dot, "    computed [label=\"?\", shape=circle, color=red];").unwrap();
+        }
+        writeln!(dot, "}}").unwrap();
+        dot
+    }
+}
+
+/// Control-flow graph of the program in `path` as DOT
+pub fn dot_file(path: &str) -> Result<String, IntcodeError> {
+    Ok(ControlFlowGraph::build(&load_input(path)?).to_dot())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::infinite_memory_intcomputer::IntcodeComputer;
+    use crate::intcode_assembler::assemble;
+
+    #[test]
+    fn blocks_and_self_modification() {
+        let program = assemble(
+            "
+                IN -> [value]
+            loop:
+                ADD [value], #-1 -> [value]
+                ADD #0, [value] -> [patched]
+                JNZ [value], #loop
+                JZ [value], [target]
+            patched:
+                OUT #0
+                HLT
+            value: DATA 0
+            target: DATA 0
+            ",
+        )
+        .unwrap();
+        let cfg = ControlFlowGraph::build(&program);
+        let starts: Vec<usize> = cfg.blocks.keys().cloned().collect();
+        assert_eq!(starts, vec![0, 2, 13, 16]);
+        assert_eq!(cfg.blocks[&0].successors, vec![2]);
+        assert_eq!(cfg.blocks[&2].successors, vec![13, 2]);
+        assert_eq!(cfg.blocks[&13].successors, vec![16]);
+        assert_eq!(cfg.computed_jumps(), vec![13]);
+        assert_eq!(cfg.input_reads(), vec![0]);
+        assert_eq!(cfg.code_writers, vec![6].into_iter().collect());
+        assert_eq!(cfg.self_modified, vec![16].into_iter().collect());
+
+        let mut cfg = cfg;
+        cfg.mark_runtime_writes(&[MemoryAccess::Write { address: 15, value: 0 }]);
+        assert!(cfg.self_modified.contains(&13));
+        let dot = cfg.to_dot();
+        assert!(dot.contains("b2 -> b13;"));
+        assert!(dot.contains("b13 -> computed [style=dashed, color=red];"));
+        assert!(dot.contains("16: OUT #0 (modified)\\l"));
+    }
+
+    #[test]
+    fn day_nine_structure() {
+        let program = load_input("input/day_nine.txt").unwrap();
+        let mut cfg = ControlFlowGraph::build(&program);
+        let reads = cfg.input_reads();
+        assert!(!reads.is_empty());
+        for block in cfg.blocks.values() {
+            for successor in &block.successors {
+                assert!(cfg.blocks.contains_key(successor));
+            }
+        }
+
+        // BOOST only writes into its data, so the self-test run agrees with the static pass
+        assert_eq!(cfg.self_modified, BTreeSet::new());
+        assert_eq!(cfg.code_writers, BTreeSet::new());
+        let mut computer = IntcodeComputer::new(program);
+        computer.record_accesses(true);
+        computer.provide_input(1);
+        computer.run_until_halt().unwrap();
+        let accesses = computer.take_accesses();
+        assert!(accesses.iter().any(|access| matches!(access, MemoryAccess::Write { .. })));
+        cfg.mark_runtime_writes(&accesses);
+        assert_eq!(cfg.self_modified, BTreeSet::new());
+        assert!(dot_file("input/day_nine.txt").unwrap().starts_with("digraph intcode {"));
+    }
+}
@@ -1,148 +1,88 @@
-use std::fs;
-use std::io;
+use crate::infinite_memory_intcomputer::{read_program, IntcodeComputer, IntcodeComputerState, IntcodeError};
+use std::convert::TryFrom;
+use std::io::{self, BufRead, Write};
 
-fn read() -> io::Result<String> {
-    let mut buffer = String::new();
-    io::stdin().read_line(&mut buffer)?;
-    Ok(buffer)
-}
-
-pub fn load_input(path: &str) -> Vec<i32> {
-    fs::read_to_string(path)
-        .expect("Something went wrong reading the file")
+pub fn load_input(path: &str) -> Result<Vec<i32>, IntcodeError> {
+    Ok(read_program(path)?
         .split(",")
-        .filter_map(|s| s.parse::<i32>().ok())
-        .collect()
+        .filter_map(|s| s.trim().parse::<i32>().ok())
+        .collect())
 }
 
-#[derive(PartialEq, Debug)]
-enum ParamMode {
-    PositionMode,
-    ImmediateMode
+/// How `try_process_intcode` feeds inputs and reports outputs
+pub enum IoMode<'a> {
+    /// Inputs are consumed in order, outputs are only returned
+    Inputs(Vec<i32>),
+    /// Asks for every input on the writer and echoes every output to it
+    Prompt(&'a mut dyn BufRead, &'a mut dyn Write),
 }
 
-fn get_op_code(op: i32) -> i32 {
-    op % 100
+fn narrow(value: i64) -> Result<i32, IntcodeError> {
+    i32::try_from(value).map_err(|_| IntcodeError::ValueOutOfRange { value })
 }
 
-fn get_param_mode(op: i32, param_index: u32) -> ParamMode{
-    let mode = op / (10_i32.pow(param_index+2)) % 10;
-    match mode {
-        0 => ParamMode::PositionMode,
-        1 => ParamMode::ImmediateMode,
-        _ => panic!("Unknown param mode"),
+fn io_error(error: io::Error) -> IntcodeError {
+    IntcodeError::Io {
+        path: "<stdio>".to_owned(),
+        message: error.to_string(),
     }
 }
 
-fn load_param(data: &Vec<i32>, index: usize, mode: ParamMode) -> i32 {
-    match mode {
-        ParamMode::ImmediateMode => data[index],
-        ParamMode::PositionMode => data[data[index] as usize],
+fn prompt(reader: &mut dyn BufRead, writer: &mut dyn Write) -> Result<i64, IntcodeError> {
+    writeln!(writer, "Write input pls").map_err(io_error)?;
+    let mut buffer = String::new();
+    if reader.read_line(&mut buffer).map_err(io_error)? == 0 {
+        return Err(IntcodeError::InputExhausted);
     }
+    let text = buffer.trim();
+    text.parse().map_err(|_| IntcodeError::InvalidInput { text: text.to_owned() })
 }
 
-pub fn process_intcode(program: &mut Vec<i32>, input: Option<Vec<i32>>) -> Vec<i32> {
-    let io_std = input.is_none();
-    let mut input = input.unwrap_or_default();
-    // Reverse vector to read data in correct order
-    input.reverse();
-    let mut program_counter = 0;
+/// Runs the program on `IntcodeComputer` and writes the final memory back into `program`
+pub fn try_process_intcode(program: &mut Vec<i32>, io: IoMode) -> Result<Vec<i32>, IntcodeError> {
+    let mut computer = IntcodeComputer::new(program.iter().map(|value| *value as i64).collect());
+    let (mut inputs, mut prompt_io) = match io {
+        IoMode::Inputs(inputs) => (inputs.into_iter(), None),
+        IoMode::Prompt(reader, writer) => (vec![].into_iter(), Some((reader, writer))),
+    };
     let mut output_buffer = vec![];
     loop {
-        let op = program[program_counter];
-        match get_op_code(op) {
-            1 => {
-                // addition
-                let a = load_param(program, program_counter + 1, get_param_mode(op, 0));
-                let b = load_param(program, program_counter + 2, get_param_mode(op, 1));
-                let output_location = program[program_counter + 3] as usize;
-                program[output_location] = a + b;
-                program_counter += 4;
-            },
-            2 => {
-                // multiplication
-                let a = load_param(program, program_counter + 1, get_param_mode(op, 0));
-                let b = load_param(program, program_counter + 2, get_param_mode(op, 1));
-                let output_location = program[program_counter + 3] as usize;
-                program[output_location] = a * b;
-                program_counter += 4;
-            },
-            3 => {
-                // input
-                let user_input = if io_std {
-                    println!("Write input pls");
-                    read()
-                        .unwrap()
-                        .trim()
-                        .parse()
-                        .unwrap()
-                } else {
-                    input.pop().unwrap()
+        match computer.run()? {
+            IntcodeComputerState::WaitingForInput => {
+                let input = match &mut prompt_io {
+                    Some((reader, writer)) => prompt(*reader, *writer)?,
+                    None => inputs.next().ok_or(IntcodeError::InputExhausted)? as i64,
                 };
-                let output_location = program[program_counter + 1] as usize;
-                program[output_location] = user_input;
-                program_counter += 2;
-            },
-            4 => {
-                // input
-                let output = load_param(program, program_counter + 1, get_param_mode(op, 0));
-                if io_std {
-                    println!("output: {}", output);
-                }
-                output_buffer.push(output);
-                program_counter += 2;
-            },
-            5 => {
-                // jump if true
-                let input = load_param(program, program_counter + 1, get_param_mode(op, 0));
-                let target = load_param(program, program_counter + 2, get_param_mode(op, 1));
-                if input != 0 {
-                    program_counter = target as usize;
-                } else {
-                    program_counter += 3;
+                computer.provide_input(input);
+            }
+            IntcodeComputerState::OutputProduced(output) => {
+                if let Some((_, writer)) = &mut prompt_io {
+                    writeln!(writer, "output: {}", output).map_err(io_error)?;
                 }
-            },
-            6 => {
-                // jump if false
-                let input = load_param(program, program_counter + 1, get_param_mode(op, 0));
-                let target = load_param(program, program_counter + 2, get_param_mode(op, 1));
-                if input == 0 {
-                    program_counter = target as usize;
-                } else {
-                    program_counter += 3;
-                }
-            },
-            7 => {
-                // less than
-                let a = load_param(program, program_counter + 1, get_param_mode(op, 0));
-                let b = load_param(program, program_counter + 2, get_param_mode(op, 1));
-                let output = program[program_counter + 3] as usize;
-                program[output] = if a < b {
-                    1
-                } else {
-                    0
-                };
-                program_counter += 4;
-            },
-            8 => {
-                // equals
-                let a = load_param(program, program_counter + 1, get_param_mode(op, 0));
-                let b = load_param(program, program_counter + 2, get_param_mode(op, 1));
-                let output = program[program_counter + 3] as usize;
-                program[output] = if a == b {
-                    1
-                } else {
-                    0
-                };
-                program_counter += 4;
-            },
-            99 => {
-                break;
-            },
-            _ => panic!("Error at {}", program_counter),
+                output_buffer.push(narrow(output)?);
+            }
+            IntcodeComputerState::Halted => break,
+            state @ (IntcodeComputerState::BudgetExhausted | IntcodeComputerState::Blocked) => {
+                return Err(IntcodeError::UnexpectedState(state))
+            }
         }
     }
-    output_buffer
+    *program = computer.dump_memory().into_iter().map(narrow).collect::<Result<_, _>>()?;
+    Ok(output_buffer)
+}
+
+/// Reads from stdin when `input` is None and panics on any error
+pub fn process_intcode(program: &mut Vec<i32>, input: Option<Vec<i32>>) -> Vec<i32> {
+    let result = match input {
+        Some(input) => try_process_intcode(program, IoMode::Inputs(input)),
+        None => {
+            let stdin = io::stdin();
+            let mut reader = stdin.lock();
+            let mut writer = io::stdout();
+            try_process_intcode(program, IoMode::Prompt(&mut reader, &mut writer))
+        }
+    };
+    result.unwrap_or_else(|error| panic!("{}", error))
 }
 
 
@@ -150,31 +90,6 @@ pub fn process_intcode(program: &mut Vec<i32>, input: Option<Vec<i32>>) -> Vec<i
 mod tests {
     use super::*;
 
-    #[test]
-    fn get_op_code_extracts(){
-        assert_eq!(get_op_code(1002), 02);
-        assert_eq!(get_op_code(2), 02);
-    }
-
-    #[test]
-    fn get_param_mode_one() {
-        assert_eq!(get_param_mode(1002, 0), ParamMode::PositionMode);
-        assert_eq!(get_param_mode(1002, 1), ParamMode::ImmediateMode);
-        assert_eq!(get_param_mode(1002, 2), ParamMode::PositionMode);
-    }
-
-    #[test]
-    fn test_load_param_immediate() {
-        let data = vec![7];
-        assert_eq!(load_param(&data, 0, ParamMode::ImmediateMode), 7);
-    }
-
-    #[test]
-    fn test_load_param_position() {
-        let data = vec![2, 4, 5, 6];
-        assert_eq!(load_param(&data, 0, ParamMode::PositionMode), 5);
-    }
-
     #[test]
     fn test_computer_one() {
         let mut input = vec![1002,4,3,4,33];
@@ -191,7 +106,7 @@ mod tests {
 
     #[test]
     fn test_computer_input() {
-        let mut program = load_input("input/day_five.txt");
+        let mut program = load_input("input/day_five.txt").unwrap();
         let output = process_intcode(&mut program, Some(vec![1]));
         assert_eq!(output.last(), Some(&9006673));
     }
@@ -316,9 +231,38 @@ mod tests {
         assert_eq!(output, vec![1001]);
     }
 
+    #[test]
+    fn prompt_mode_reads_and_echoes() {
+        let mut program = vec![3, 9, 8, 9, 10, 9, 4, 9, 99, -1, 8];
+        let mut reader = &b" 8\n"[..];
+        let mut writer = vec![];
+        let output = try_process_intcode(&mut program, IoMode::Prompt(&mut reader, &mut writer)).unwrap();
+        assert_eq!(output, vec![1]);
+        assert_eq!(String::from_utf8(writer).unwrap(), "Write input pls\noutput: 1\n");
+    }
+
+    #[test]
+    fn reports_errors_instead_of_panicking() {
+        let mut program = vec![3, 0, 99];
+        assert_eq!(
+            try_process_intcode(&mut program, IoMode::Inputs(vec![])),
+            Err(IntcodeError::InputExhausted)
+        );
+        let mut reader = &b"eight\n"[..];
+        assert_eq!(
+            try_process_intcode(&mut program, IoMode::Prompt(&mut reader, &mut vec![])),
+            Err(IntcodeError::InvalidInput { text: "eight".to_owned() })
+        );
+        let mut program = vec![1002, 5, 3, 5, 99, 1_000_000_000];
+        assert_eq!(
+            try_process_intcode(&mut program, IoMode::Inputs(vec![])),
+            Err(IntcodeError::ValueOutOfRange { value: 3_000_000_000 })
+        );
+    }
+
     #[test]
     fn day_5_task_2() {
-        let mut program = load_input("input/day_five.txt");
+        let mut program = load_input("input/day_five.txt").unwrap();
         let input = vec![5];
         let output = process_intcode(&mut program, Some(input));
         assert_eq!(output, vec![3629692]);
@@ -0,0 +1,371 @@
+use crate::infinite_memory_intcomputer::{IntcodeComputer, IntcodeComputerState, IntcodeError, MemoryAccess};
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+use std::io::{self, BufRead, Write};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchKind {
+    Read,
+    Write,
+    ReadWrite,
+}
+
+impl WatchKind {
+    fn matches(&self, access: &MemoryAccess) -> bool {
+        matches!(
+            (self, access),
+            (WatchKind::ReadWrite, _)
+                | (WatchKind::Read, MemoryAccess::Read { .. })
+                | (WatchKind::Write, MemoryAccess::Write { .. })
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StopReason {
+    Stepped,
+    Breakpoint(i32),
+    Watchpoint(MemoryAccess),
+    WaitingForInput,
+    Halted,
+}
+
+#[derive(Debug, Clone)]
+pub struct Stop {
+    pub reason: StopReason,
+    pub instruction_pointer: i32,
+    pub relative_base: i32,
+    pub input_queue: Vec<i64>,
+    pub output_queue: Vec<i64>,
+}
+
+impl fmt::Display for Stop {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.reason {
+            StopReason::Stepped => write!(f, "stepped")?,
+            StopReason::Breakpoint(address) => write!(f, "breakpoint at {}", address)?,
+            StopReason::Watchpoint(MemoryAccess::Read { address, value }) => {
+                write!(f, "watchpoint: read {} from [{}]", value, address)?
+            }
+            StopReason::Watchpoint(MemoryAccess::Write { address, value }) => {
+                write!(f, "watchpoint: wrote {} to [{}]", value, address)?
+            }
+            StopReason::WaitingForInput => write!(f, "waiting for input")?,
+            StopReason::Halted => write!(f, "halted")?,
+        }
+        writeln!(f)?;
+        writeln!(f, "  ip: {}  rb: {}", self.instruction_pointer, self.relative_base)?;
+        writeln!(f, "  input: {:?}", self.input_queue)?;
+        write!(f, "  output: {:?}", self.output_queue)
+    }
+}
+
+pub struct Debugger {
+    computer: IntcodeComputer,
+    breakpoints: HashSet<i32>,
+    watchpoints: HashMap<i32, WatchKind>,
+}
+
+impl Debugger {
+    pub fn new(mut computer: IntcodeComputer) -> Debugger {
+        computer.record_accesses(true);
+        Debugger {
+            computer,
+            breakpoints: HashSet::new(),
+            watchpoints: HashMap::new(),
+        }
+    }
+
+    pub fn computer(&self) -> &IntcodeComputer {
+        &self.computer
+    }
+
+    pub fn computer_mut(&mut self) -> &mut IntcodeComputer {
+        &mut self.computer
+    }
+
+    pub fn into_computer(mut self) -> IntcodeComputer {
+        self.computer.record_accesses(false);
+        self.computer
+    }
+
+    pub fn add_breakpoint(&mut self, address: i32) {
+        self.breakpoints.insert(address);
+    }
+
+    pub fn remove_breakpoint(&mut self, address: i32) -> bool {
+        self.breakpoints.remove(&address)
+    }
+
+    pub fn add_watchpoint(&mut self, address: i32, kind: WatchKind) {
+        self.watchpoints.insert(address, kind);
+    }
+
+    pub fn remove_watchpoint(&mut self, address: i32) -> bool {
+        self.watchpoints.remove(&address).is_some()
+    }
+
+    fn stop(&self, reason: StopReason) -> Stop {
+        Stop {
+            reason,
+            instruction_pointer: self.computer.instruction_pointer(),
+            relative_base: self.computer.relative_base(),
+            input_queue: self.computer.input_queue().iter().cloned().collect(),
+            output_queue: self.computer.output_queue().iter().cloned().collect(),
+        }
+    }
+
+    pub fn state(&self) -> Stop {
+        self.stop(StopReason::Stepped)
+    }
+
+    pub fn step(&mut self) -> Result<Stop, IntcodeError> {
+        let state = self.computer.step()?;
+        let accesses = self.computer.take_accesses();
+        let triggered = accesses.into_iter().find(|access| {
+            let address = match access {
+                MemoryAccess::Read { address, .. } => address,
+                MemoryAccess::Write { address, .. } => address,
+            };
+            match self.watchpoints.get(address) {
+                Some(kind) => kind.matches(access),
+                None => false,
+            }
+        });
+        let reason = match (triggered, state) {
+            (Some(access), _) => StopReason::Watchpoint(access),
+            (None, Some(IntcodeComputerState::WaitingForInput)) => StopReason::WaitingForInput,
+            (None, Some(IntcodeComputerState::Halted)) => StopReason::Halted,
+            (None, _) => StopReason::Stepped,
+        };
+        Ok(self.stop(reason))
+    }
+
+    /// Runs until a breakpoint, watchpoint, input request or halt
+    pub fn continue_execution(&mut self) -> Result<Stop, IntcodeError> {
+        // always execute at least one instruction so we can leave a breakpoint
+        let mut stop = self.step()?;
+        while stop.reason == StopReason::Stepped {
+            let ip = self.computer.instruction_pointer();
+            if self.breakpoints.contains(&ip) {
+                return Ok(self.stop(StopReason::Breakpoint(ip)));
+            }
+            stop = self.step()?;
+        }
+        Ok(stop)
+    }
+}
+
+const HELP: &str = "commands:
+  s, step [n]               execute n instructions
+  c, continue               run until breakpoint, watchpoint, input or halt
+  b, break <addr>           set breakpoint
+  d, delete <addr>          remove breakpoint
+  w, watch <addr> [r|w|rw]  set memory watchpoint
+  u, unwatch <addr>         remove watchpoint
+  i, input <v>...           queue input values
+  x, mem <addr> [count]     print memory
+  r, regs                   print machine state
+  q, quit";
+
+fn parse_args(args: &[&str]) -> Result<Vec<i64>, Box<dyn Error>> {
+    let mut values = vec![];
+    for arg in args {
+        values.push(arg.parse::<i64>().map_err(|_| format!("Not a number {}", arg))?);
+    }
+    Ok(values)
+}
+
+fn to_address(value: i64) -> Result<i32, Box<dyn Error>> {
+    Ok(i32::try_from(value).map_err(|_| format!("Address out of range {}", value))?)
+}
+
+fn parse_addresses(args: &[&str]) -> Result<Vec<i32>, Box<dyn Error>> {
+    parse_args(args)?.into_iter().map(to_address).collect()
+}
+
+fn execute_command<W: Write>(debugger: &mut Debugger, line: &str, output: &mut W) -> Result<bool, Box<dyn Error>> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let (command, args) = match words.split_first() {
+        Some((command, args)) => (*command, args),
+        None => return Ok(true),
+    };
+    match command {
+        "s" | "step" => {
+            let count = parse_args(args)?.first().cloned().unwrap_or(1);
+            let mut stop = debugger.state();
+            for _ in 0..count {
+                stop = debugger.step()?;
+                if stop.reason != StopReason::Stepped {
+                    break;
+                }
+            }
+            writeln!(output, "{}", stop)?;
+        }
+        "c" | "continue" => {
+            let stop = debugger.continue_execution()?;
+            writeln!(output, "{}", stop)?;
+        }
+        "b" | "break" => {
+            for address in parse_addresses(args)? {
+                debugger.add_breakpoint(address);
+                writeln!(output, "breakpoint set at {}", address)?;
+            }
+        }
+        "d" | "delete" => {
+            for address in parse_addresses(args)? {
+                if !debugger.remove_breakpoint(address) {
+                    writeln!(output, "no breakpoint at {}", address)?;
+                }
+            }
+        }
+        "w" | "watch" => {
+            let address = match args.first() {
+                Some(arg) => parse_addresses(&[arg])?[0],
+                None => Err("Missing watch address")?,
+            };
+            let kind = match args.get(1) {
+                None | Some(&"rw") => WatchKind::ReadWrite,
+                Some(&"r") => WatchKind::Read,
+                Some(&"w") => WatchKind::Write,
+                Some(other) => Err(format!("Unknown watch kind {}", other))?,
+            };
+            debugger.add_watchpoint(address, kind);
+            writeln!(output, "watching {} for {:?}", address, kind)?;
+        }
+        "u" | "unwatch" => {
+            for address in parse_addresses(args)? {
+                if !debugger.remove_watchpoint(address) {
+                    writeln!(output, "no watchpoint at {}", address)?;
+                }
+            }
+        }
+        "i" | "input" => {
+            debugger.computer_mut().provide_input_iter(parse_args(args)?);
+        }
+        "x" | "mem" => {
+            let args = parse_args(args)?;
+            let address = to_address(*args.first().ok_or("Missing memory address")?)?;
+            let count = i32::try_from(args.get(1).cloned().unwrap_or(1)).map_err(|_| "Count out of range")?;
+            let end = address.checked_add(count).ok_or("Memory range ends past the last address")?;
+            for location in address..end {
+                writeln!(output, "[{}] = {}", location, debugger.computer().peek_memory(location)?)?;
+            }
+        }
+        "r" | "regs" => writeln!(output, "{}", debugger.state())?,
+        "h" | "help" => writeln!(output, "{}", HELP)?,
+        "q" | "quit" => return Ok(false),
+        _ => writeln!(output, "unknown command {}, try help", command)?,
+    }
+    Ok(true)
+}
+
+pub fn repl<R: BufRead, W: Write>(debugger: &mut Debugger, input: R, mut output: W) -> Result<(), Box<dyn Error>> {
+    write!(output, "(intcode) ")?;
+    output.flush()?;
+    for line in input.lines() {
+        match execute_command(debugger, &line?, &mut output) {
+            Ok(true) => (),
+            Ok(false) => return Ok(()),
+            Err(error) => writeln!(output, "error: {}", error)?,
+        }
+        write!(output, "(intcode) ")?;
+        output.flush()?;
+    }
+    Ok(())
+}
+
+pub fn debug_file(path: &str) -> Result<(), Box<dyn Error>> {
+    let mut debugger = Debugger::new(IntcodeComputer::new_from_file(path)?);
+    let stdin = io::stdin();
+    repl(&mut debugger, stdin.lock(), io::stdout())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stops_on_breakpoint() {
+        let program = vec![1101, 1, 2, 9, 1101, 3, 4, 10, 99, 0, 0];
+        let mut debugger = Debugger::new(IntcodeComputer::new(program));
+        debugger.add_breakpoint(4);
+        let stop = debugger.continue_execution().unwrap();
+        assert_eq!(stop.reason, StopReason::Breakpoint(4));
+        assert_eq!(debugger.computer().peek_memory(9).unwrap(), 3);
+        assert_eq!(debugger.computer().peek_memory(10).unwrap(), 0);
+        let stop = debugger.continue_execution().unwrap();
+        assert_eq!(stop.reason, StopReason::Halted);
+    }
+
+    #[test]
+    fn stops_on_write_watchpoint() {
+        let program = vec![1101, 1, 2, 9, 1001, 9, 4, 10, 99, 0, 0];
+        let mut debugger = Debugger::new(IntcodeComputer::new(program));
+        debugger.add_watchpoint(10, WatchKind::Write);
+        let stop = debugger.continue_execution().unwrap();
+        assert_eq!(stop.reason, StopReason::Watchpoint(MemoryAccess::Write { address: 10, value: 7 }));
+        assert_eq!(stop.instruction_pointer, 8);
+    }
+
+    #[test]
+    fn stops_on_read_watchpoint() {
+        let program = vec![1101, 1, 2, 9, 1001, 9, 4, 10, 99, 0, 0];
+        let mut debugger = Debugger::new(IntcodeComputer::new(program));
+        debugger.add_watchpoint(9, WatchKind::Read);
+        let stop = debugger.continue_execution().unwrap();
+        assert_eq!(stop.reason, StopReason::Watchpoint(MemoryAccess::Read { address: 9, value: 3 }));
+        assert_eq!(stop.instruction_pointer, 8);
+    }
+
+    #[test]
+    fn step_reports_queues() {
+        let program = vec![3, 9, 109, 5, 4, 9, 99, 0, 0, 0];
+        let mut debugger = Debugger::new(IntcodeComputer::new(program));
+        assert_eq!(debugger.step().unwrap().reason, StopReason::WaitingForInput);
+        debugger.computer_mut().provide_input_iter(vec![42, 43]);
+        let stop = debugger.step().unwrap();
+        assert_eq!(stop.input_queue, vec![43]);
+        let stop = debugger.step().unwrap();
+        assert_eq!(stop.relative_base, 5);
+        let stop = debugger.step().unwrap();
+        assert_eq!(stop.output_queue, vec![42]);
+        assert_eq!(stop.instruction_pointer, 6);
+    }
+
+    #[test]
+    fn repl_session() {
+        let program = vec![3, 9, 1002, 9, 2, 9, 4, 9, 99, 0];
+        let mut debugger = Debugger::new(IntcodeComputer::new(program));
+        let commands = "b 6\ni 21\nc\nx 9\nc\nq\n";
+        let mut output = vec![];
+        repl(&mut debugger, commands.as_bytes(), &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("breakpoint at 6"));
+        assert!(output.contains("[9] = 42"));
+        assert!(output.contains("halted"));
+        assert!(output.contains("output: [42]"));
+    }
+
+    #[test]
+    fn rejects_addresses_outside_i32() {
+        let mut debugger = Debugger::new(IntcodeComputer::new(vec![99]));
+        let commands = "x 2147483647 2
+b 4294967296
+w -2147483649 r
+d 2147483648
+x 0 1
+q
+";
+        let mut output = vec![];
+        repl(&mut debugger, commands.as_bytes(), &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("error: Memory range ends past the last address"));
+        assert!(output.contains("error: Address out of range 4294967296"));
+        assert!(output.contains("error: Address out of range -2147483649"));
+        assert!(output.contains("error: Address out of range 2147483648"));
+        assert!(output.contains("[0] = 99"));
+    }
+}
@@ -0,0 +1,408 @@
+use crate::infinite_memory_intcomputer::{get_op_code, get_param_mode, ParamMode};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+const DATA_PER_LINE: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Opcode {
+    Add,
+    Mul,
+    In,
+    Out,
+    Jnz,
+    Jz,
+    Lt,
+    Eq,
+    Arb,
+    Hlt,
+}
+
+const OPCODES: [Opcode; 10] = [
+    Opcode::Add,
+    Opcode::Mul,
+    Opcode::In,
+    Opcode::Out,
+    Opcode::Jnz,
+    Opcode::Jz,
+    Opcode::Lt,
+    Opcode::Eq,
+    Opcode::Arb,
+    Opcode::Hlt,
+];
+
+impl Opcode {
+    pub fn from_code(code: i64) -> Option<Opcode> {
+        OPCODES.iter().cloned().find(|opcode| opcode.code() == code)
+    }
+
+    pub fn from_mnemonic(mnemonic: &str) -> Option<Opcode> {
+        OPCODES
+            .iter()
+            .cloned()
+            .find(|opcode| opcode.mnemonic().eq_ignore_ascii_case(mnemonic))
+    }
+
+    pub fn code(&self) -> i64 {
+        match self {
+            Opcode::Add => 1,
+            Opcode::Mul => 2,
+            Opcode::In => 3,
+            Opcode::Out => 4,
+            Opcode::Jnz => 5,
+            Opcode::Jz => 6,
+            Opcode::Lt => 7,
+            Opcode::Eq => 8,
+            Opcode::Arb => 9,
+            Opcode::Hlt => 99,
+        }
+    }
+
+    pub fn mnemonic(&self) -> &'static str {
+        match self {
+            Opcode::Add => "ADD",
+            Opcode::Mul => "MUL",
+            Opcode::In => "IN",
+            Opcode::Out => "OUT",
+            Opcode::Jnz => "JNZ",
+            Opcode::Jz => "JZ",
+            Opcode::Lt => "LT",
+            Opcode::Eq => "EQ",
+            Opcode::Arb => "ARB",
+            Opcode::Hlt => "HLT",
+        }
+    }
+
+    pub fn param_count(&self) -> usize {
+        match self {
+            Opcode::Add | Opcode::Mul | Opcode::Lt | Opcode::Eq => 3,
+            Opcode::Jnz | Opcode::Jz => 2,
+            Opcode::In | Opcode::Out | Opcode::Arb => 1,
+            Opcode::Hlt => 0,
+        }
+    }
+
+    /// Whether the last parameter is an address the instruction writes to
+    pub fn writes_last_param(&self) -> bool {
+        matches!(self, Opcode::Add | Opcode::Mul | Opcode::Lt | Opcode::Eq | Opcode::In)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Operand {
+    pub mode: ParamMode,
+    pub value: i64,
+}
+
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.mode {
+            ParamMode::ImmediateMode => write!(f, "#{}", self.value),
+            ParamMode::PositionMode => write!(f, "[{}]", self.value),
+            ParamMode::RelativeMode if self.value < 0 => write!(f, "[rb{}]", self.value),
+            ParamMode::RelativeMode => write!(f, "[rb+{}]", self.value),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Instruction {
+    pub address: usize,
+    pub opcode: Opcode,
+    pub operands: Vec<Operand>,
+}
+
+impl Instruction {
+    pub fn len(&self) -> usize {
+        1 + self.operands.len()
+    }
+
+    pub fn encode(&self) -> Vec<i64> {
+        let mut op = self.opcode.code();
+        for (i, operand) in self.operands.iter().enumerate() {
+            op += mode_digit(operand.mode) * 10_i64.pow(i as u32 + 2);
+        }
+        let mut words = vec![op];
+        words.extend(self.operands.iter().map(|operand| operand.value));
+        words
+    }
+
+    /// Jump destination when it is known without running the program
+    pub fn static_jump_target(&self) -> Option<i64> {
+        match self.opcode {
+            Opcode::Jnz | Opcode::Jz if self.operands[1].mode == ParamMode::ImmediateMode => {
+                Some(self.operands[1].value)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn is_computed_jump(&self) -> bool {
+        match self.opcode {
+            Opcode::Jnz | Opcode::Jz => self.operands[1].mode != ParamMode::ImmediateMode,
+            _ => false,
+        }
+    }
+
+    /// Whether execution can continue with the next instruction in memory
+    pub fn falls_through(&self) -> bool {
+        let condition = &self.operands.first();
+        match (self.opcode, condition) {
+            (Opcode::Hlt, _) => false,
+            (Opcode::Jnz, Some(Operand { mode: ParamMode::ImmediateMode, value })) => *value == 0,
+            (Opcode::Jz, Some(Operand { mode: ParamMode::ImmediateMode, value })) => *value != 0,
+            _ => true,
+        }
+    }
+
+    fn render(&self, labels: &BTreeMap<usize, String>) -> String {
+        let target_label = match self.static_jump_target() {
+            Some(target) if target >= 0 => labels.get(&(target as usize)),
+            _ => None,
+        };
+        let operand = |index: usize| -> String {
+            match target_label {
+                Some(label) if index == 1 => format!("#{}", label),
+                _ => self.operands[index].to_string(),
+            }
+        };
+        let mnemonic = self.opcode.mnemonic();
+        match self.opcode {
+            Opcode::Add | Opcode::Mul | Opcode::Lt | Opcode::Eq => {
+                format!("{} {}, {} -> {}", mnemonic, operand(0), operand(1), operand(2))
+            }
+            Opcode::In => format!("{} -> {}", mnemonic, operand(0)),
+            Opcode::Out | Opcode::Arb => format!("{} {}", mnemonic, operand(0)),
+            Opcode::Jnz | Opcode::Jz => format!("{} {}, {}", mnemonic, operand(0), operand(1)),
+            Opcode::Hlt => mnemonic.to_owned(),
+        }
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.render(&BTreeMap::new()))
+    }
+}
+
+pub fn mode_digit(mode: ParamMode) -> i64 {
+    match mode {
+        ParamMode::PositionMode => 0,
+        ParamMode::ImmediateMode => 1,
+        ParamMode::RelativeMode => 2,
+    }
+}
+
+/// Decodes the instruction at `address`, `None` if the memory there isn't a valid instruction
+pub fn decode(memory: &[i64], address: usize) -> Option<Instruction> {
+    let op = *memory.get(address)?;
+    if op < 0 {
+        return None;
+    }
+    let opcode = Opcode::from_code(get_op_code(&op))?;
+    let mut operands = vec![];
+    for i in 0..opcode.param_count() {
+        let mode = get_param_mode(&op, i as u32)?;
+        if mode == ParamMode::ImmediateMode && opcode.writes_last_param() && i + 1 == opcode.param_count() {
+            return None;
+        }
+        let value = *memory.get(address + 1 + i)?;
+        operands.push(Operand { mode, value });
+    }
+    let instruction = Instruction {
+        address,
+        opcode,
+        operands,
+    };
+    // stray mode digits would not survive a round trip
+    if instruction.encode()[0] != op {
+        return None;
+    }
+    Some(instruction)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ListingLine {
+    Instruction(Instruction),
+    Data { address: usize, values: Vec<i64> },
+}
+
+#[derive(Debug, Clone)]
+pub struct Listing {
+    pub lines: Vec<ListingLine>,
+    pub labels: BTreeMap<usize, String>,
+}
+
+impl Listing {
+    pub fn instructions(&self) -> impl Iterator<Item = &Instruction> {
+        self.lines.iter().filter_map(|line| match line {
+            ListingLine::Instruction(instruction) => Some(instruction),
+            ListingLine::Data { .. } => None,
+        })
+    }
+}
+
+impl fmt::Display for Listing {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for line in &self.lines {
+            let (address, text) = match line {
+                ListingLine::Instruction(instruction) => (instruction.address, instruction.render(&self.labels)),
+                ListingLine::Data { address, values } => {
+                    let values: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+                    (*address, format!("DATA {}", values.join(", ")))
+                }
+            };
+            if let Some(label) = self.labels.get(&address) {
+                writeln!(f, "{}:", label)?;
+            }
+            writeln!(f, "    {:<40} ; {}", text, address)?;
+        }
+        Ok(())
+    }
+}
+
+/// Finds instructions reachable from the entry point. Static jump targets are followed and
+/// immediates written to memory (usually return addresses) are tried as extra entry points.
+pub(crate) fn find_code(memory: &[i64]) -> (BTreeMap<usize, Instruction>, BTreeSet<usize>) {
+    let mut code: BTreeMap<usize, Instruction> = BTreeMap::new();
+    let mut covered = BTreeSet::new();
+    let mut entry_points = BTreeSet::new();
+    let mut flow = vec![0];
+    let mut candidates = vec![];
+    loop {
+        let (address, is_candidate) = match flow.pop() {
+            Some(address) => (address, false),
+            None => match candidates.pop() {
+                Some(address) => (address, true),
+                None => break,
+            },
+        };
+        if code.contains_key(&address) {
+            continue;
+        }
+        let instruction = match decode(memory, address) {
+            Some(instruction) => instruction,
+            None => continue,
+        };
+        if (address..address + instruction.len()).any(|a| covered.contains(&a)) {
+            continue;
+        }
+        covered.extend(address..address + instruction.len());
+        if is_candidate {
+            entry_points.insert(address);
+        }
+        if instruction.falls_through() {
+            flow.push(address + instruction.len());
+        }
+        if let Some(target) = instruction.static_jump_target() {
+            if target >= 0 {
+                flow.push(target as usize);
+            }
+        }
+        if let Opcode::Add | Opcode::Mul = instruction.opcode {
+            for operand in &instruction.operands[..2] {
+                if operand.mode == ParamMode::ImmediateMode && operand.value > 0 {
+                    candidates.push(operand.value as usize);
+                }
+            }
+        }
+        code.insert(address, instruction);
+    }
+    (code, entry_points)
+}
+
+pub fn disassemble(memory: &[i64]) -> Listing {
+    let (code, entry_points) = find_code(memory);
+    let mut labels = BTreeMap::new();
+    let targets = code
+        .values()
+        .filter_map(|instruction| instruction.static_jump_target())
+        .filter(|target| *target >= 0)
+        .map(|target| target as usize)
+        .chain(entry_points);
+    for target in targets {
+        if code.contains_key(&target) {
+            labels.insert(target, format!("L{}", target));
+        }
+    }
+
+    let mut lines = vec![];
+    let mut address = 0;
+    while address < memory.len() {
+        if let Some(instruction) = code.get(&address) {
+            address += instruction.len();
+            lines.push(ListingLine::Instruction(instruction.clone()));
+            continue;
+        }
+        let start = address;
+        let mut values = vec![];
+        while address < memory.len()
+            && !code.contains_key(&address)
+            && values.len() < DATA_PER_LINE
+            && (address == start || !labels.contains_key(&address))
+        {
+            values.push(memory[address]);
+            address += 1;
+        }
+        lines.push(ListingLine::Data { address: start, values });
+    }
+    Listing { lines, labels }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::infinite_memory_intcomputer::{load_input, IntcodeComputer};
+
+    #[test]
+    fn decodes_modes() {
+        let instruction = decode(&[1201, 3, 5, 100], 0).unwrap();
+        assert_eq!(instruction.to_string(), "ADD [rb+3], #5 -> [100]");
+        assert_eq!(instruction.encode(), vec![1201, 3, 5, 100]);
+        let instruction = decode(&[204, -1], 0).unwrap();
+        assert_eq!(instruction.to_string(), "OUT [rb-1]");
+    }
+
+    #[test]
+    fn rejects_invalid_instructions() {
+        // unknown opcode, unknown mode, immediate write, stray mode digit, truncated
+        assert_eq!(decode(&[42], 0), None);
+        assert_eq!(decode(&[304, 0], 0), None);
+        assert_eq!(decode(&[11101, 1, 1, 5], 0), None);
+        assert_eq!(decode(&[1099], 0), None);
+        assert_eq!(decode(&[1, 0, 0], 0), None);
+    }
+
+    #[test]
+    fn data_after_halt() {
+        let listing = disassemble(&[1002, 4, 3, 4, 33]);
+        assert_eq!(
+            listing.lines,
+            vec![
+                ListingLine::Instruction(decode(&[1002, 4, 3, 4], 0).unwrap()),
+                ListingLine::Data { address: 4, values: vec![33] },
+            ]
+        );
+    }
+
+    #[test]
+    fn labels_jump_targets() {
+        let program = vec![3, 12, 6, 12, 15, 1, 13, 14, 13, 4, 13, 99, -1, 0, 1, 9];
+        let listing = disassemble(&program).to_string();
+        assert!(listing.contains("JZ [12], [15]"));
+        let program = vec![3, 3, 1105, -1, 9, 1101, 0, 0, 12, 4, 12, 99, 1];
+        let listing = disassemble(&program).to_string();
+        assert!(listing.contains("JNZ #-1, #L9"));
+        assert!(listing.contains("L9:\n    OUT [12]"));
+    }
+
+    #[test]
+    fn disassembles_computer_memory() {
+        let computer = IntcodeComputer::new(load_input("input/day_nine.txt").unwrap());
+        let listing = disassemble(&computer.dump_memory());
+        let first = listing.instructions().next().unwrap();
+        assert_eq!(first.to_string(), "MUL #34463338, #34463338 -> [63]");
+        assert!(listing.instructions().any(|i| i.opcode == Opcode::Arb));
+        assert!(listing.instructions().any(|i| i.opcode == Opcode::In));
+    }
+}
@@ -0,0 +1,433 @@
+use crate::infinite_memory_intcomputer::{IntcodeComputer, IntcodeError, ParamMode};
+use crate::intcode_disassembler::{Instruction, Opcode, Operand};
+use crate::intcode_wide::WideIntcodeComputer;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::convert::TryFrom;
+use std::fmt;
+
+/// Words that don't decode, each fails differently
+const BROKEN_WORDS: [i64; 5] = [0, 42, 98, 304, 11101];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Arg {
+    Immediate(i64),
+    /// Position mode pointing at a data slot
+    Data(usize),
+    Relative(i64),
+}
+
+/// Instruction of a generated program. Writes only go to data slots and jumps only go
+/// forward, so every program terminates and never changes its code.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Op {
+    /// Add, Mul, Lt or Eq
+    Arithmetic { opcode: Opcode, a: Arg, b: Arg, target: usize },
+    In { target: usize },
+    Out { value: Arg },
+    /// Jnz or Jz to the instruction `skip` places after the next one, or the final halt
+    Jump { opcode: Opcode, condition: Arg, skip: usize },
+    Arb { amount: i64 },
+    Broken(i64),
+}
+
+impl Op {
+    fn len(&self) -> usize {
+        match self {
+            Op::Arithmetic { .. } => 4,
+            Op::Jump { .. } => 3,
+            Op::In { .. } | Op::Out { .. } | Op::Arb { .. } => 2,
+            Op::Broken(_) => 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Case {
+    pub ops: Vec<Op>,
+    pub data: Vec<i64>,
+    pub inputs: Vec<i64>,
+}
+
+impl Case {
+    /// Random case, the same seed always gives the same case
+    pub fn generate(rng: &mut StdRng) -> Case {
+        let data: Vec<i64> = (0..rng.gen_range(1, 6)).map(|_| rng.gen_range(-100, 100)).collect();
+        let ops = (0..rng.gen_range(1, 20)).map(|_| random_op(rng, data.len())).collect();
+        let inputs = (0..rng.gen_range(0, 4)).map(|_| rng.gen_range(-100, 100)).collect();
+        Case { ops, data, inputs }
+    }
+
+    /// The ops followed by a halt and the data slots
+    pub fn program(&self) -> Vec<i64> {
+        let mut addresses = vec![];
+        let mut address = 0;
+        for op in &self.ops {
+            addresses.push(address);
+            address += op.len();
+        }
+        let halt = address;
+        let data_start = halt + 1;
+        let operand = |arg: &Arg| match arg {
+            Arg::Immediate(value) => Operand { mode: ParamMode::ImmediateMode, value: *value },
+            Arg::Data(slot) => Operand { mode: ParamMode::PositionMode, value: (data_start + slot) as i64 },
+            Arg::Relative(offset) => Operand { mode: ParamMode::RelativeMode, value: *offset },
+        };
+        let mut program = vec![];
+        for (index, op) in self.ops.iter().enumerate() {
+            let (opcode, operands) = match op {
+                Op::Arithmetic { opcode, a, b, target } => (*opcode, vec![operand(a), operand(b), operand(&Arg::Data(*target))]),
+                Op::In { target } => (Opcode::In, vec![operand(&Arg::Data(*target))]),
+                Op::Out { value } => (Opcode::Out, vec![operand(value)]),
+                Op::Jump { opcode, condition, skip } => {
+                    let target = addresses.get(index + 1 + skip).cloned().unwrap_or(halt);
+                    (*opcode, vec![operand(condition), operand(&Arg::Immediate(target as i64))])
+                }
+                Op::Arb { amount } => (Opcode::Arb, vec![operand(&Arg::Immediate(*amount))]),
+                Op::Broken(word) => {
+                    program.push(*word);
+                    continue;
+                }
+            };
+            let instruction = Instruction { address: addresses[index], opcode, operands };
+            program.extend(instruction.encode());
+        }
+        program.push(Opcode::Hlt.code());
+        program.extend(&self.data);
+        program
+    }
+
+    /// Smaller variants: an op or input less, a value closer to zero
+    fn simplifications(&self) -> Vec<Case> {
+        let mut candidates = vec![];
+        for index in 0..self.ops.len() {
+            let mut case = self.clone();
+            case.ops.remove(index);
+            candidates.push(case);
+        }
+        for index in 0..self.inputs.len() {
+            let mut case = self.clone();
+            case.inputs.remove(index);
+            candidates.push(case);
+        }
+        let used_slots = self.ops.iter().map(max_slot).max().unwrap_or(0);
+        if self.data.len() > used_slots {
+            let mut case = self.clone();
+            case.data.truncate(used_slots);
+            candidates.push(case);
+        }
+        for index in 0..self.ops.len() {
+            for op in simpler_ops(&self.ops[index]) {
+                let mut case = self.clone();
+                case.ops[index] = op;
+                candidates.push(case);
+            }
+        }
+        for index in 0..self.data.len() {
+            if self.data[index] != 0 {
+                let mut case = self.clone();
+                case.data[index] /= 2;
+                candidates.push(case);
+            }
+        }
+        for index in 0..self.inputs.len() {
+            if self.inputs[index] != 0 {
+                let mut case = self.clone();
+                case.inputs[index] /= 2;
+                candidates.push(case);
+            }
+        }
+        candidates
+    }
+}
+
+fn random_arg(rng: &mut StdRng, slots: usize) -> Arg {
+    match rng.gen_range(0, 3) {
+        0 => Arg::Immediate(rng.gen_range(-50, 50)),
+        1 => Arg::Data(rng.gen_range(0, slots)),
+        _ => Arg::Relative(rng.gen_range(-5, 40)),
+    }
+}
+
+fn random_op(rng: &mut StdRng, slots: usize) -> Op {
+    match rng.gen_range(0, 100) {
+        0..=39 => {
+            let opcode = [Opcode::Add, Opcode::Mul, Opcode::Lt, Opcode::Eq][rng.gen_range(0, 4)];
+            // small factors keep products of a whole program well inside i64
+            let b = match opcode {
+                Opcode::Mul => Arg::Immediate(rng.gen_range(-3, 4)),
+                _ => random_arg(rng, slots),
+            };
+            Op::Arithmetic { opcode, a: random_arg(rng, slots), b, target: rng.gen_range(0, slots) }
+        }
+        40..=49 => Op::In { target: rng.gen_range(0, slots) },
+        50..=69 => Op::Out { value: random_arg(rng, slots) },
+        70..=84 => Op::Jump {
+            opcode: if rng.gen_bool(0.5) { Opcode::Jnz } else { Opcode::Jz },
+            condition: random_arg(rng, slots),
+            skip: rng.gen_range(0, 4),
+        },
+        85..=96 => Op::Arb { amount: rng.gen_range(-10, 20) },
+        _ => Op::Broken(BROKEN_WORDS[rng.gen_range(0, BROKEN_WORDS.len())]),
+    }
+}
+
+/// Number of data slots the op needs
+fn max_slot(op: &Op) -> usize {
+    let slot = |arg: &Arg| match arg {
+        Arg::Data(slot) => slot + 1,
+        _ => 0,
+    };
+    match op {
+        Op::Arithmetic { a, b, target, .. } => slot(a).max(slot(b)).max(target + 1),
+        Op::In { target } => target + 1,
+        Op::Out { value } => slot(value),
+        Op::Jump { condition, .. } => slot(condition),
+        Op::Arb { .. } | Op::Broken(_) => 0,
+    }
+}
+
+fn simpler_args(arg: &Arg) -> Vec<Arg> {
+    match arg {
+        Arg::Immediate(0) => vec![],
+        Arg::Immediate(value) => vec![Arg::Immediate(0), Arg::Immediate(value / 2)],
+        _ => vec![Arg::Immediate(0)],
+    }
+}
+
+fn simpler_ops(op: &Op) -> Vec<Op> {
+    match op {
+        Op::Arithmetic { opcode, a, b, target } => {
+            let mut ops: Vec<Op> = simpler_args(a)
+                .into_iter()
+                .map(|a| Op::Arithmetic { opcode: *opcode, a, b: b.clone(), target: *target })
+                .collect();
+            ops.extend(
+                simpler_args(b)
+                    .into_iter()
+                    .map(|b| Op::Arithmetic { opcode: *opcode, a: a.clone(), b, target: *target }),
+            );
+            ops
+        }
+        Op::Out { value } => simpler_args(value).into_iter().map(|value| Op::Out { value }).collect(),
+        Op::Jump { opcode, condition, skip } => {
+            let mut ops: Vec<Op> = simpler_args(condition)
+                .into_iter()
+                .map(|condition| Op::Jump { opcode: *opcode, condition, skip: *skip })
+                .collect();
+            if *skip > 0 {
+                ops.push(Op::Jump { opcode: *opcode, condition: condition.clone(), skip: 0 });
+            }
+            ops
+        }
+        Op::Arb { amount } if *amount != 0 => vec![Op::Arb { amount: 0 }],
+        _ => vec![],
+    }
+}
+
+/// What running a program produced. Outputs and memory are only compared for runs that halt,
+/// failed runs are compared by the kind of error.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    Halted { outputs: Vec<i64>, memory: Vec<i64> },
+    Failed(&'static str),
+}
+
+impl Outcome {
+    fn halted(outputs: Vec<i64>, mut memory: Vec<i64>) -> Outcome {
+        // untouched memory past the program reads as zeros on every engine
+        while memory.last() == Some(&0) {
+            memory.pop();
+        }
+        Outcome::Halted { outputs, memory }
+    }
+}
+
+pub fn error_kind(error: &IntcodeError) -> &'static str {
+    match error {
+        IntcodeError::UnknownOpcode { .. } => "unknown opcode",
+        IntcodeError::InvalidParamMode { .. } => "invalid param mode",
+        IntcodeError::NegativeAddress { .. } => "negative address",
+        IntcodeError::ImmediateWrite { .. } => "immediate write",
+        IntcodeError::InputExhausted => "input exhausted",
+        IntcodeError::InvalidInput { .. } => "invalid input",
+        IntcodeError::ValueOutOfRange { .. } => "value out of range",
+        IntcodeError::Io { .. } => "io",
+        IntcodeError::InputNotAvailable { .. } => "input not available",
+        IntcodeError::Overflow { .. } => "overflow",
+        IntcodeError::AddressOutOfRange { .. } => "address out of range",
+        IntcodeError::UnexpectedState(_) => "unexpected state",
+    }
+}
+
+/// An interpreter the fuzzer compares against the others
+pub trait Engine {
+    fn name(&self) -> &str;
+    /// `None` when the engine can't run the case at all
+    fn run(&self, program: &[i64], inputs: &[i64]) -> Option<Outcome>;
+}
+
+/// `IntcodeComputer`, optionally with its decode cache
+pub struct Interpreter {
+    pub decode_cache: bool,
+}
+
+impl Engine for Interpreter {
+    fn name(&self) -> &str {
+        if self.decode_cache {
+            "decode cache"
+        } else {
+            "interpreter"
+        }
+    }
+
+    fn run(&self, program: &[i64], inputs: &[i64]) -> Option<Outcome> {
+        let mut computer = IntcodeComputer::new(program.to_vec());
+        computer.set_decode_cache(self.decode_cache);
+        computer.provide_input_iter(inputs.iter().cloned());
+        Some(match computer.run_until_halt() {
+            Ok(outputs) => Outcome::halted(outputs, computer.dump_memory()),
+            Err(error) => Outcome::Failed(error_kind(&error)),
+        })
+    }
+}
+
+/// `WideIntcodeComputer`, skips cases whose results don't fit back into i64
+pub struct Wide;
+
+impl Engine for Wide {
+    fn name(&self) -> &str {
+        "wide"
+    }
+
+    fn run(&self, program: &[i64], inputs: &[i64]) -> Option<Outcome> {
+        let narrow = |values: Vec<i128>| values.into_iter().map(|value| i64::try_from(value).ok()).collect::<Option<Vec<i64>>>();
+        let mut computer = WideIntcodeComputer::from_program(program);
+        computer.provide_input_iter(inputs.iter().map(|value| i128::from(*value)));
+        Some(match computer.run_until_halt() {
+            Ok(outputs) => Outcome::halted(narrow(outputs)?, narrow(computer.dump_memory())?),
+            Err(error) => Outcome::Failed(error_kind(&error)),
+        })
+    }
+}
+
+/// Both `IntcodeComputer` modes checked against `WideIntcodeComputer`, the separately written reference
+pub fn engines() -> Vec<Box<dyn Engine>> {
+    vec![
+        Box::new(Interpreter { decode_cache: false }),
+        Box::new(Interpreter { decode_cache: true }),
+        Box::new(Wide),
+    ]
+}
+
+/// A case the engines disagree on, with what each of them produced
+#[derive(Debug, Clone)]
+pub struct Mismatch {
+    pub case: Case,
+    pub outcomes: Vec<(String, Outcome)>,
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let program: Vec<String> = self.case.program().iter().map(|word| word.to_string()).collect();
+        writeln!(f, "program: {}", program.join(","))?;
+        writeln!(f, "inputs: {:?}", self.case.inputs)?;
+        for (engine, outcome) in &self.outcomes {
+            writeln!(f, "{}: {:?}", engine, outcome)?;
+        }
+        Ok(())
+    }
+}
+
+/// Runs the case on every engine that can run it, `None` when they all agree
+pub fn check(case: &Case, engines: &[Box<dyn Engine>]) -> Option<Mismatch> {
+    let program = case.program();
+    let outcomes: Vec<(String, Outcome)> = engines
+        .iter()
+        .filter_map(|engine| Some((engine.name().to_owned(), engine.run(&program, &case.inputs)?)))
+        .collect();
+    if outcomes.windows(2).all(|pair| pair[0].1 == pair[1].1) {
+        return None;
+    }
+    Some(Mismatch { case: case.clone(), outcomes })
+}
+
+/// Keeps taking the first simplification that still mismatches until none does
+pub fn shrink(mismatch: Mismatch, engines: &[Box<dyn Engine>]) -> Mismatch {
+    let mut smallest = mismatch;
+    while let Some(smaller) = smallest
+        .case
+        .simplifications()
+        .iter()
+        .find_map(|candidate| check(candidate, engines))
+    {
+        smallest = smaller;
+    }
+    smallest
+}
+
+/// Checks `cases` random programs from `seed`, the first mismatch comes back shrunk
+pub fn fuzz(seed: u64, cases: usize, engines: &[Box<dyn Engine>]) -> Option<Mismatch> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    (0..cases)
+        .find_map(|_| check(&Case::generate(&mut rng), engines))
+        .map(|mismatch| shrink(mismatch, engines))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn engines_agree() {
+        if let Some(mismatch) = fuzz(2019, 500, &engines()) {
+            panic!("engines disagree\n{}", mismatch);
+        }
+    }
+
+    #[test]
+    fn generated_programs_cover_errors_and_halts() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let outcomes: Vec<Outcome> = (0..200)
+            .map(|_| {
+                let case = Case::generate(&mut rng);
+                Interpreter { decode_cache: false }.run(&case.program(), &case.inputs).unwrap()
+            })
+            .collect();
+        assert!(outcomes.iter().any(|outcome| matches!(outcome, Outcome::Halted { outputs, .. } if !outputs.is_empty())));
+        for kind in &["unknown opcode", "negative address", "input exhausted"] {
+            assert!(outcomes.contains(&Outcome::Failed(kind)), "no {} case", kind);
+        }
+    }
+
+    /// Loses every output after the first
+    struct Forgetful;
+
+    impl Engine for Forgetful {
+        fn name(&self) -> &str {
+            "forgetful"
+        }
+
+        fn run(&self, program: &[i64], inputs: &[i64]) -> Option<Outcome> {
+            let interpreter = Interpreter { decode_cache: false };
+            match interpreter.run(program, inputs)? {
+                Outcome::Halted { mut outputs, memory } => {
+                    outputs.truncate(1);
+                    Some(Outcome::Halted { outputs, memory })
+                }
+                failed => Some(failed),
+            }
+        }
+    }
+
+    #[test]
+    fn shrinks_to_minimal_program() {
+        let engines: Vec<Box<dyn Engine>> = vec![Box::new(Interpreter { decode_cache: false }), Box::new(Forgetful)];
+        let mismatch = fuzz(1, 1000, &engines).unwrap();
+        let out = Op::Out { value: Arg::Immediate(0) };
+        assert_eq!(mismatch.case.ops, vec![out.clone(), out]);
+        assert!(mismatch.case.inputs.is_empty());
+        assert!(mismatch.case.data.is_empty());
+        assert_eq!(mismatch.case.program(), vec![104, 0, 104, 0, 99]);
+    }
+}
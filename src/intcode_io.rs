@@ -0,0 +1,213 @@
+use crate::infinite_memory_intcomputer::IntcodeError;
+use std::collections::VecDeque;
+use std::io::{self, BufRead, StdinLock, Stdout, Write};
+use std::sync::mpsc::{Receiver, Sender};
+
+/// Source of inputs and sink for outputs for `IntcodeComputer::run_with_io`
+pub trait IntcodeIo {
+    /// Next input, `None` when the source has no data and the computer has to block
+    fn read(&mut self) -> Result<Option<i64>, IntcodeError>;
+    fn write(&mut self, value: i64) -> Result<(), IntcodeError>;
+}
+
+impl<T: IntcodeIo + ?Sized> IntcodeIo for &mut T {
+    fn read(&mut self) -> Result<Option<i64>, IntcodeError> {
+        (**self).read()
+    }
+
+    fn write(&mut self, value: i64) -> Result<(), IntcodeError> {
+        (**self).write(value)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct QueueIo {
+    pub input: VecDeque<i64>,
+    pub output: VecDeque<i64>,
+}
+
+impl QueueIo {
+    pub fn new<T: IntoIterator<Item = i64>>(input: T) -> QueueIo {
+        QueueIo {
+            input: input.into_iter().collect(),
+            output: VecDeque::new(),
+        }
+    }
+}
+
+impl IntcodeIo for QueueIo {
+    fn read(&mut self) -> Result<Option<i64>, IntcodeError> {
+        Ok(self.input.pop_front())
+    }
+
+    fn write(&mut self, value: i64) -> Result<(), IntcodeError> {
+        self.output.push_back(value);
+        Ok(())
+    }
+}
+
+pub struct CallbackIo<I, O> {
+    read: I,
+    write: O,
+}
+
+impl<I: FnMut() -> Option<i64>, O: FnMut(i64)> CallbackIo<I, O> {
+    pub fn new(read: I, write: O) -> CallbackIo<I, O> {
+        CallbackIo { read, write }
+    }
+}
+
+impl<I: FnMut() -> Option<i64>, O: FnMut(i64)> IntcodeIo for CallbackIo<I, O> {
+    fn read(&mut self) -> Result<Option<i64>, IntcodeError> {
+        Ok((self.read)())
+    }
+
+    fn write(&mut self, value: i64) -> Result<(), IntcodeError> {
+        (self.write)(value);
+        Ok(())
+    }
+}
+
+/// Waits on the receiver for input, only blocks the computer once every sender is gone
+pub struct ChannelIo {
+    receiver: Receiver<i64>,
+    sender: Sender<i64>,
+}
+
+impl ChannelIo {
+    pub fn new(receiver: Receiver<i64>, sender: Sender<i64>) -> ChannelIo {
+        ChannelIo { receiver, sender }
+    }
+}
+
+impl IntcodeIo for ChannelIo {
+    fn read(&mut self) -> Result<Option<i64>, IntcodeError> {
+        Ok(self.receiver.recv().ok())
+    }
+
+    fn write(&mut self, value: i64) -> Result<(), IntcodeError> {
+        self.sender.send(value).map_err(|_| IntcodeError::Io {
+            path: "<channel>".to_owned(),
+            message: "output receiver was dropped".to_owned(),
+        })
+    }
+}
+
+fn io_error(error: io::Error) -> IntcodeError {
+    IntcodeError::Io {
+        path: "<stdio>".to_owned(),
+        message: error.to_string(),
+    }
+}
+
+/// One number per line in, one number per line out. End of input blocks the computer.
+pub struct StdIo<R, W> {
+    reader: R,
+    writer: W,
+}
+
+impl StdIo<StdinLock<'static>, Stdout> {
+    pub fn new() -> StdIo<StdinLock<'static>, Stdout> {
+        StdIo::with(io::stdin().lock(), io::stdout())
+    }
+}
+
+impl Default for StdIo<StdinLock<'static>, Stdout> {
+    fn default() -> Self {
+        StdIo::new()
+    }
+}
+
+impl<R: BufRead, W: Write> StdIo<R, W> {
+    pub fn with(reader: R, writer: W) -> StdIo<R, W> {
+        StdIo { reader, writer }
+    }
+}
+
+impl<R: BufRead, W: Write> IntcodeIo for StdIo<R, W> {
+    fn read(&mut self) -> Result<Option<i64>, IntcodeError> {
+        let mut line = String::new();
+        if self.reader.read_line(&mut line).map_err(io_error)? == 0 {
+            return Ok(None);
+        }
+        let text = line.trim();
+        text.parse()
+            .map(Some)
+            .map_err(|_| IntcodeError::InvalidInput { text: text.to_owned() })
+    }
+
+    fn write(&mut self, value: i64) -> Result<(), IntcodeError> {
+        writeln!(self.writer, "{}", value).map_err(io_error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::infinite_memory_intcomputer::{IntcodeComputer, IntcodeComputerState};
+    use crate::intcode_assembler::assemble;
+    use std::sync::mpsc::channel;
+    use std::thread;
+
+    fn doubler() -> IntcodeComputer {
+        let source = "
+            ; doubles every input until it reads a 0
+            loop:
+                IN -> [value]
+                JZ [value], #end
+                MUL [value], #2 -> [value]
+                OUT [value]
+                JNZ #1, #loop
+            end:
+                HLT
+            value: DATA 0
+        ";
+        IntcodeComputer::new(assemble(source).unwrap())
+    }
+
+    #[test]
+    fn queue_blocks_when_empty() {
+        let mut computer = doubler();
+        let mut io = QueueIo::new(vec![1, 2]);
+        assert_eq!(computer.run_with_io(&mut io).unwrap(), IntcodeComputerState::Blocked);
+        assert_eq!(io.output, vec![2, 4]);
+        assert!(computer.output_queue().is_empty());
+        io.input.push_back(0);
+        assert_eq!(computer.run_with_io(&mut io).unwrap(), IntcodeComputerState::Halted);
+    }
+
+    #[test]
+    fn callbacks() {
+        let mut inputs = vec![0, 5, 3];
+        let mut outputs = vec![];
+        let io = CallbackIo::new(|| inputs.pop(), |value| outputs.push(value));
+        assert_eq!(doubler().run_with_io(io).unwrap(), IntcodeComputerState::Halted);
+        assert_eq!(outputs, vec![6, 10]);
+    }
+
+    #[test]
+    fn channels_between_threads() {
+        let (input, receiver) = channel();
+        let (sender, output) = channel();
+        let mut computer = doubler();
+        let handle = thread::spawn(move || computer.run_with_io(ChannelIo::new(receiver, sender)));
+        input.send(21).unwrap();
+        assert_eq!(output.recv().unwrap(), 42);
+        drop(input);
+        assert_eq!(handle.join().unwrap().unwrap(), IntcodeComputerState::Blocked);
+    }
+
+    #[test]
+    fn text_lines() {
+        let mut written = vec![];
+        let io = StdIo::with(&b"7\n-1\n"[..], &mut written);
+        assert_eq!(doubler().run_with_io(io).unwrap(), IntcodeComputerState::Blocked);
+        assert_eq!(String::from_utf8(written).unwrap(), "14\n-2\n");
+
+        let io = StdIo::with(&b"seven\n"[..], vec![]);
+        assert_eq!(
+            doubler().run_with_io(io).unwrap_err(),
+            IntcodeError::InvalidInput { text: "seven".to_owned() }
+        );
+    }
+}
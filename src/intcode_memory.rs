@@ -0,0 +1,162 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+const PAGE_BITS: usize = 10;
+pub const PAGE_SIZE: usize = 1 << PAGE_BITS;
+// pages below this index live in a vector, everything above in a map
+const DENSE_PAGES: usize = 1024;
+
+pub type Page = [i64; PAGE_SIZE];
+
+/// Paged Intcode memory. Pages are shared between clones and copied on first write.
+#[derive(Clone, Default)]
+pub struct Memory {
+    dense: Vec<Option<Arc<Page>>>,
+    sparse: HashMap<usize, Arc<Page>>,
+    len: usize,
+}
+
+impl Memory {
+    pub fn new(program: &[i64]) -> Memory {
+        let mut memory = Memory::default();
+        for (page_index, chunk) in program.chunks(PAGE_SIZE).enumerate() {
+            let mut page = [0; PAGE_SIZE];
+            page[..chunk.len()].copy_from_slice(chunk);
+            memory.insert_page(page_index, Arc::new(page));
+        }
+        memory.len = program.len();
+        memory
+    }
+
+    /// Rebuilds memory from `pages()` output, `None` if a page address isn't page aligned
+    pub fn from_pages(pages: Vec<(usize, Page)>, len: usize) -> Option<Memory> {
+        let mut memory = Memory::default();
+        for (address, page) in pages {
+            if address % PAGE_SIZE != 0 {
+                return None;
+            }
+            memory.insert_page(address >> PAGE_BITS, Arc::new(page));
+        }
+        memory.len = len;
+        Some(memory)
+    }
+
+    fn insert_page(&mut self, page_index: usize, page: Arc<Page>) {
+        if page_index < DENSE_PAGES {
+            if self.dense.len() <= page_index {
+                self.dense.resize(page_index + 1, None);
+            }
+            self.dense[page_index] = Some(page);
+        } else {
+            self.sparse.insert(page_index, page);
+        }
+    }
+
+    fn page(&self, page_index: usize) -> Option<&Arc<Page>> {
+        if page_index < DENSE_PAGES {
+            self.dense.get(page_index).and_then(|page| page.as_ref())
+        } else {
+            self.sparse.get(&page_index)
+        }
+    }
+
+    fn page_mut(&mut self, page_index: usize) -> &mut Page {
+        let page = if page_index < DENSE_PAGES {
+            if self.dense.len() <= page_index {
+                self.dense.resize(page_index + 1, None);
+            }
+            self.dense[page_index].get_or_insert_with(|| Arc::new([0; PAGE_SIZE]))
+        } else {
+            self.sparse
+                .entry(page_index)
+                .or_insert_with(|| Arc::new([0; PAGE_SIZE]))
+        };
+        Arc::make_mut(page)
+    }
+
+    pub fn get(&self, address: usize) -> i64 {
+        match self.page(address >> PAGE_BITS) {
+            Some(page) => page[address & (PAGE_SIZE - 1)],
+            None => 0,
+        }
+    }
+
+    pub fn set(&mut self, address: usize, value: i64) {
+        self.page_mut(address >> PAGE_BITS)[address & (PAGE_SIZE - 1)] = value;
+        if address >= self.len {
+            self.len = address + 1;
+        }
+    }
+
+    /// One past the highest address that was loaded or written
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Allocated pages as `(first address, values)` in address order
+    pub fn pages(&self) -> Vec<(usize, &Page)> {
+        let mut pages: Vec<(usize, &Page)> = self
+            .dense
+            .iter()
+            .enumerate()
+            .filter_map(|(index, page)| page.as_ref().map(|page| (index, &**page)))
+            .chain(self.sparse.iter().map(|(index, page)| (*index, &**page)))
+            .map(|(index, page)| (index << PAGE_BITS, page))
+            .collect();
+        pages.sort_by_key(|(address, _)| *address);
+        pages
+    }
+
+    pub fn to_vec(&self) -> Vec<i64> {
+        (0..self.len).map(|address| self.get(address)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_program_and_defaults_to_zero() {
+        let memory = Memory::new(&[1, 2, 3]);
+        assert_eq!(memory.get(2), 3);
+        assert_eq!(memory.get(3), 0);
+        assert_eq!(memory.get(5_000_000_000), 0);
+        assert_eq!(memory.to_vec(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn grows_on_write() {
+        let mut memory = Memory::new(&[1]);
+        memory.set(PAGE_SIZE * 3 + 7, 42);
+        assert_eq!(memory.len(), PAGE_SIZE * 3 + 8);
+        assert_eq!(memory.get(PAGE_SIZE * 3 + 7), 42);
+        assert_eq!(memory.pages().len(), 2);
+    }
+
+    #[test]
+    fn sparse_high_addresses() {
+        let mut memory = Memory::new(&[]);
+        let address = DENSE_PAGES * PAGE_SIZE * 1000 + 5;
+        memory.set(address, -9);
+        assert_eq!(memory.get(address), -9);
+        assert!(memory.dense.is_empty());
+        assert_eq!(memory.pages()[0].0, address - 5);
+    }
+
+    #[test]
+    fn clones_share_until_written() {
+        let mut original = Memory::new(&[1, 2, 3]);
+        let mut copy = original.clone();
+        assert!(Arc::ptr_eq(original.page(0).unwrap(), copy.page(0).unwrap()));
+        copy.set(1, 20);
+        assert!(!Arc::ptr_eq(original.page(0).unwrap(), copy.page(0).unwrap()));
+        original.set(2, 30);
+        assert_eq!(original.to_vec(), vec![1, 2, 30]);
+        assert_eq!(copy.to_vec(), vec![1, 20, 3]);
+    }
+}
@@ -0,0 +1,475 @@
+use crate::infinite_memory_intcomputer::{IntcodeComputer, IntcodeComputerState, IntcodeError};
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{channel, RecvTimeoutError, Sender, TryRecvError};
+use std::thread;
+use std::time::Duration;
+
+// instructions a machine may run between input requests before the scheduler moves on
+const TURN_BUDGET: u64 = 10_000;
+// consecutive empty reads after which a machine counts as idle
+const IDLE_READS: usize = 2;
+const IDLE_CHECK: Duration = Duration::from_millis(1);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Packet {
+    pub destination: i64,
+    pub x: i64,
+    pub y: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NatAction {
+    Continue,
+    Send(Packet),
+    Stop,
+}
+
+/// Monitor for packets addressed outside the network and for network idle
+pub trait Nat {
+    fn receive(&mut self, packet: Packet) -> NatAction;
+    /// Called once every machine is idle and no packets are queued. `Continue` ends the run
+    /// with `NetworkStop::Idle` since nothing would happen anymore.
+    fn idle(&mut self) -> NatAction;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NetworkStop {
+    Nat,
+    Idle,
+    AllHalted,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NetworkError {
+    pub address: usize,
+    pub error: IntcodeError,
+}
+
+impl fmt::Display for NetworkError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "machine {}: {}", self.address, self.error)
+    }
+}
+
+impl Error for NetworkError {}
+
+/// Machines addressed 0 to size - 1, each boots with its address as first input
+pub struct Network {
+    computers: Vec<IntcodeComputer>,
+}
+
+impl Network {
+    pub fn new(program: &[i64], size: usize) -> Network {
+        let computers = (0..size)
+            .map(|address| {
+                let mut computer = IntcodeComputer::new(program.to_vec());
+                computer.provide_input(address as i64);
+                computer
+            })
+            .collect();
+        Network { computers }
+    }
+
+    pub fn computers(&self) -> &[IntcodeComputer] {
+        &self.computers
+    }
+
+    /// Runs the machines round robin on the current thread, results only depend on the programs
+    pub fn run(&mut self, nat: &mut dyn Nat) -> Result<NetworkStop, NetworkError> {
+        let size = self.computers.len();
+        let mut queues: Vec<VecDeque<Packet>> = vec![VecDeque::new(); size];
+        let mut partial: Vec<Vec<i64>> = vec![vec![]; size];
+        let mut idle_reads = vec![0; size];
+        let mut waiting = vec![false; size];
+        let mut halted = vec![false; size];
+        loop {
+            for address in 0..size {
+                if halted[address] {
+                    // nobody is going to read them
+                    queues[address].clear();
+                    continue;
+                }
+                let computer = &mut self.computers[address];
+                if waiting[address] {
+                    if queues[address].is_empty() {
+                        computer.provide_input(-1);
+                        idle_reads[address] += 1;
+                    } else {
+                        for packet in queues[address].drain(..) {
+                            computer.provide_input_iter(vec![packet.x, packet.y]);
+                        }
+                        idle_reads[address] = 0;
+                    }
+                    waiting[address] = false;
+                }
+                let mut packets = vec![];
+                loop {
+                    let state = computer
+                        .run_with_budget(TURN_BUDGET)
+                        .map_err(|error| NetworkError { address, error })?;
+                    match state {
+                        IntcodeComputerState::OutputProduced(value) => {
+                            partial[address].push(value);
+                            if let [destination, x, y] = partial[address][..] {
+                                packets.push(Packet { destination, x, y });
+                                partial[address].clear();
+                                idle_reads[address] = 0;
+                            }
+                        }
+                        IntcodeComputerState::WaitingForInput => {
+                            waiting[address] = true;
+                            break;
+                        }
+                        IntcodeComputerState::Halted => {
+                            halted[address] = true;
+                            break;
+                        }
+                        IntcodeComputerState::BudgetExhausted | IntcodeComputerState::Blocked => {
+                            idle_reads[address] = 0;
+                            break;
+                        }
+                    }
+                }
+                for packet in packets {
+                    if route(packet, nat, &mut queues) {
+                        return Ok(NetworkStop::Nat);
+                    }
+                }
+            }
+            if halted.iter().all(|halted| *halted) {
+                return Ok(NetworkStop::AllHalted);
+            }
+            let idle = (0..size).all(|a| halted[a] || (idle_reads[a] >= IDLE_READS && queues[a].is_empty()));
+            if idle {
+                match nat.idle() {
+                    NatAction::Continue => return Ok(NetworkStop::Idle),
+                    NatAction::Stop => return Ok(NetworkStop::Nat),
+                    NatAction::Send(packet) => deliver(packet, &mut queues),
+                }
+            }
+        }
+    }
+
+    /// Runs every machine on its own thread, the NAT runs on the calling thread
+    pub fn run_threaded(&mut self, nat: &mut dyn Nat) -> Result<NetworkStop, NetworkError> {
+        let size = self.computers.len();
+        let stop = AtomicBool::new(false);
+        // packets sent but not yet read by their destination
+        let in_flight = AtomicUsize::new(0);
+        // bumped on every packet so idle has to hold across two checks
+        let activity = AtomicUsize::new(0);
+        let idle: Vec<AtomicBool> = (0..size).map(|_| AtomicBool::new(false)).collect();
+        let (events, received) = channel::<Result<Packet, NetworkError>>();
+        let mut inboxes = vec![];
+
+        thread::scope(|scope| {
+            for (address, computer) in self.computers.iter_mut().enumerate() {
+                let (inbox, packets) = channel::<Packet>();
+                inboxes.push(inbox);
+                let events = events.clone();
+                let (stop, in_flight, activity, idle) = (&stop, &in_flight, &activity, &idle[address]);
+                scope.spawn(move || {
+                    let mut partial = vec![];
+                    let mut idle_reads = 0;
+                    while !stop.load(Ordering::SeqCst) {
+                        let state = match computer.run_with_budget(TURN_BUDGET) {
+                            Ok(state) => state,
+                            Err(error) => {
+                                let _ = events.send(Err(NetworkError { address, error }));
+                                return;
+                            }
+                        };
+                        match state {
+                            IntcodeComputerState::WaitingForInput => match packets.try_recv() {
+                                Ok(packet) => {
+                                    idle.store(false, Ordering::SeqCst);
+                                    idle_reads = 0;
+                                    computer.provide_input_iter(vec![packet.x, packet.y]);
+                                    in_flight.fetch_sub(1, Ordering::SeqCst);
+                                }
+                                Err(TryRecvError::Empty) => {
+                                    idle_reads += 1;
+                                    if idle_reads >= IDLE_READS {
+                                        idle.store(true, Ordering::SeqCst);
+                                    }
+                                    computer.provide_input(-1);
+                                    thread::yield_now();
+                                }
+                                Err(TryRecvError::Disconnected) => return,
+                            },
+                            IntcodeComputerState::OutputProduced(value) => {
+                                partial.push(value);
+                                if let [destination, x, y] = partial[..] {
+                                    partial.clear();
+                                    idle_reads = 0;
+                                    idle.store(false, Ordering::SeqCst);
+                                    in_flight.fetch_add(1, Ordering::SeqCst);
+                                    activity.fetch_add(1, Ordering::SeqCst);
+                                    if events.send(Ok(Packet { destination, x, y })).is_err() {
+                                        return;
+                                    }
+                                }
+                            }
+                            IntcodeComputerState::Halted => {
+                                idle.store(true, Ordering::SeqCst);
+                                // the NAT sees the channel disconnect once every machine halted
+                                drop(events);
+                                // keep draining the inbox so packets sent here aren't counted in flight forever
+                                while !stop.load(Ordering::SeqCst) {
+                                    match packets.recv_timeout(IDLE_CHECK) {
+                                        Ok(_) => {
+                                            in_flight.fetch_sub(1, Ordering::SeqCst);
+                                        }
+                                        Err(RecvTimeoutError::Timeout) => (),
+                                        Err(RecvTimeoutError::Disconnected) => return,
+                                    }
+                                }
+                                return;
+                            }
+                            IntcodeComputerState::BudgetExhausted | IntcodeComputerState::Blocked => {
+                                idle_reads = 0;
+                                idle.store(false, Ordering::SeqCst);
+                            }
+                        }
+                    }
+                });
+            }
+            // only the machines hold senders, so the channel disconnects once all of them stopped
+            drop(events);
+
+            let deliver = |packet: Packet, inboxes: &[Sender<Packet>]| {
+                if let Some(inbox) = machine_address(&packet, size).map(|address| &inboxes[address]) {
+                    in_flight.fetch_add(1, Ordering::SeqCst);
+                    activity.fetch_add(1, Ordering::SeqCst);
+                    let _ = inbox.send(packet);
+                }
+            };
+            let mut last_activity = usize::MAX;
+            let result = loop {
+                match received.recv_timeout(IDLE_CHECK) {
+                    Ok(Ok(packet)) => match machine_address(&packet, size) {
+                        Some(address) => {
+                            let _ = inboxes[address].send(packet);
+                        }
+                        None => {
+                            in_flight.fetch_sub(1, Ordering::SeqCst);
+                            match nat.receive(packet) {
+                                NatAction::Continue => (),
+                                NatAction::Stop => break Ok(NetworkStop::Nat),
+                                NatAction::Send(packet) => deliver(packet, &inboxes),
+                            }
+                        }
+                    },
+                    Ok(Err(error)) => break Err(error),
+                    Err(RecvTimeoutError::Disconnected) => break Ok(NetworkStop::AllHalted),
+                    Err(RecvTimeoutError::Timeout) => {
+                        let current = activity.load(Ordering::SeqCst);
+                        let quiet = in_flight.load(Ordering::SeqCst) == 0
+                            && idle.iter().all(|idle| idle.load(Ordering::SeqCst));
+                        if !quiet || current != last_activity {
+                            last_activity = current;
+                            continue;
+                        }
+                        match nat.idle() {
+                            NatAction::Continue => break Ok(NetworkStop::Idle),
+                            NatAction::Stop => break Ok(NetworkStop::Nat),
+                            NatAction::Send(packet) => deliver(packet, &inboxes),
+                        }
+                    }
+                }
+            };
+            stop.store(true, Ordering::SeqCst);
+            inboxes.clear();
+            result
+        })
+    }
+}
+
+fn machine_address(packet: &Packet, size: usize) -> Option<usize> {
+    if packet.destination >= 0 && (packet.destination as usize) < size {
+        Some(packet.destination as usize)
+    } else {
+        None
+    }
+}
+
+/// Queues a packet or hands it to the NAT, true if the NAT stopped the network
+fn route(packet: Packet, nat: &mut dyn Nat, queues: &mut [VecDeque<Packet>]) -> bool {
+    match machine_address(&packet, queues.len()) {
+        Some(address) => queues[address].push_back(packet),
+        None => match nat.receive(packet) {
+            NatAction::Continue => (),
+            NatAction::Stop => return true,
+            NatAction::Send(packet) => deliver(packet, queues),
+        },
+    }
+    false
+}
+
+/// NAT packets can only go to machines, anything else is dropped
+fn deliver(packet: Packet, queues: &mut [VecDeque<Packet>]) {
+    if let Some(address) = machine_address(&packet, queues.len()) {
+        queues[address].push_back(packet);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode_assembler::assemble;
+
+    const SIZE: usize = 5;
+    const HOPS: i64 = 20;
+
+    /// Machine 0 starts a token around the ring, each hop bumps y and the machine that
+    /// sees y reach HOPS reports to address 255 instead of passing it on
+    fn ring() -> Vec<i64> {
+        let source = format!(
+            "
+                IN -> [addr]
+                JNZ [addr], #wait
+                OUT #1
+                OUT #0
+                OUT #0
+            wait:
+                IN -> [x]
+                EQ [x], #-1 -> [tmp]
+                JNZ [tmp], #wait
+                IN -> [y]
+                ADD [y], #1 -> [y]
+                LT [y], #{hops} -> [tmp]
+                JZ [tmp], #done
+                ADD [addr], #1 -> [next]
+                EQ [next], #{size} -> [tmp]
+                JZ [tmp], #send
+                ADD #0, #0 -> [next]
+            send:
+                OUT [next]
+                OUT [x]
+                OUT [y]
+                JNZ #1, #wait
+            done:
+                OUT #255
+                OUT [addr]
+                OUT [y]
+                JNZ #1, #wait
+            addr: DATA 0
+            next: DATA 0
+            x: DATA 0
+            y: DATA 0
+            tmp: DATA 0
+            ",
+            hops = HOPS,
+            size = SIZE
+        );
+        assemble(&source).unwrap()
+    }
+
+    /// Restarts the ring the way machine 0 starts it on idle until it was idle `restarts` times
+    #[derive(Default)]
+    struct Monitor {
+        received: Vec<Packet>,
+        idles: usize,
+        restarts: usize,
+        stop_on_receive: bool,
+    }
+
+    impl Nat for Monitor {
+        fn receive(&mut self, packet: Packet) -> NatAction {
+            self.received.push(packet);
+            if self.stop_on_receive {
+                NatAction::Stop
+            } else {
+                NatAction::Continue
+            }
+        }
+
+        fn idle(&mut self) -> NatAction {
+            self.idles += 1;
+            if self.idles > self.restarts {
+                NatAction::Continue
+            } else {
+                NatAction::Send(Packet { destination: 1, x: 0, y: 0 })
+            }
+        }
+    }
+
+    fn expected(count: usize) -> Vec<Packet> {
+        let packet = Packet { destination: 255, x: HOPS % SIZE as i64, y: HOPS };
+        vec![packet; count]
+    }
+
+    #[test]
+    fn nat_receives_outside_packets() {
+        let mut monitor = Monitor { stop_on_receive: true, ..Monitor::default() };
+        assert_eq!(Network::new(&ring(), SIZE).run(&mut monitor), Ok(NetworkStop::Nat));
+        assert_eq!(monitor.received, expected(1));
+        assert_eq!(monitor.idles, 0);
+    }
+
+    #[test]
+    fn nat_restarts_idle_network() {
+        let mut monitor = Monitor { restarts: 2, ..Monitor::default() };
+        assert_eq!(Network::new(&ring(), SIZE).run(&mut monitor), Ok(NetworkStop::Idle));
+        assert_eq!(monitor.received, expected(3));
+        assert_eq!(monitor.idles, 3);
+    }
+
+    #[test]
+    fn threaded_matches_deterministic() {
+        let mut monitor = Monitor { restarts: 2, ..Monitor::default() };
+        assert_eq!(Network::new(&ring(), SIZE).run_threaded(&mut monitor), Ok(NetworkStop::Idle));
+        assert_eq!(monitor.received, expected(3));
+        assert_eq!(monitor.idles, 3);
+
+        let mut monitor = Monitor { stop_on_receive: true, ..Monitor::default() };
+        assert_eq!(Network::new(&ring(), SIZE).run_threaded(&mut monitor), Ok(NetworkStop::Nat));
+        assert_eq!(monitor.received, expected(1));
+    }
+
+    #[test]
+    fn halting_and_failing_machines() {
+        let mut monitor = Monitor::default();
+        let program = assemble("IN -> [0]\nHLT").unwrap();
+        assert_eq!(Network::new(&program, 3).run(&mut monitor), Ok(NetworkStop::AllHalted));
+        assert_eq!(Network::new(&program, 3).run_threaded(&mut monitor), Ok(NetworkStop::AllHalted));
+
+        // machine 2 jumps past the end of the program
+        let program = assemble("IN -> [a]\nEQ [a], #2 -> [a]\nJNZ [a], #11\nHLT\na: DATA 0").unwrap();
+        let error = NetworkError { address: 2, error: IntcodeError::UnknownOpcode { op: 0, ip: 11 } };
+        assert_eq!(Network::new(&program, 3).run(&mut monitor), Err(error.clone()));
+        assert_eq!(Network::new(&program, 3).run_threaded(&mut monitor), Err(error));
+    }
+
+    #[test]
+    fn packets_to_halted_machines_are_dropped() {
+        // machine 0 sends to machine 1, which halts right away, then waits forever
+        let program = assemble(
+            "
+                IN -> [addr]
+                JNZ [addr], #halt
+                OUT #1
+                OUT #7
+                OUT #8
+            wait:
+                IN -> [x]
+                JNZ #1, #wait
+            halt:
+                HLT
+            addr: DATA 0
+            x: DATA 0
+            ",
+        )
+        .unwrap();
+        let mut monitor = Monitor::default();
+        assert_eq!(Network::new(&program, 2).run(&mut monitor), Ok(NetworkStop::Idle));
+        assert_eq!(monitor.idles, 1);
+        let mut monitor = Monitor::default();
+        assert_eq!(Network::new(&program, 2).run_threaded(&mut monitor), Ok(NetworkStop::Idle));
+        assert_eq!(monitor.idles, 1);
+        assert!(monitor.received.is_empty());
+    }
+}
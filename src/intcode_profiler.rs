@@ -0,0 +1,204 @@
+use crate::infinite_memory_intcomputer::{IntcodeComputer, IntcodeError, MemoryAccess};
+use crate::intcode_cfg::ControlFlowGraph;
+use crate::intcode_disassembler::{decode, Instruction, Opcode};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+/// Counts collected by `IntcodeComputer::set_profiling`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Profile {
+    pub instructions: BTreeMap<usize, u64>,
+    pub opcodes: BTreeMap<Opcode, u64>,
+    pub reads: BTreeMap<usize, u64>,
+    pub writes: BTreeMap<usize, u64>,
+}
+
+/// Executions of one basic block
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockProfile {
+    pub start: usize,
+    pub end: usize,
+    /// How often its first instruction ran
+    pub entries: u64,
+    pub instructions: u64,
+    /// `None` where the code wasn't found statically, e.g. behind a computed jump
+    pub listing: Option<Vec<Instruction>>,
+}
+
+impl Profile {
+    pub(crate) fn count_instruction(&mut self, address: usize, opcode: Opcode) {
+        *self.instructions.entry(address).or_insert(0) += 1;
+        *self.opcodes.entry(opcode).or_insert(0) += 1;
+    }
+
+    pub(crate) fn count_access(&mut self, access: &MemoryAccess) {
+        let (counts, address) = match access {
+            MemoryAccess::Read { address, .. } => (&mut self.reads, address),
+            MemoryAccess::Write { address, .. } => (&mut self.writes, address),
+        };
+        *counts.entry(*address as usize).or_insert(0) += 1;
+    }
+
+    pub fn total_instructions(&self) -> u64 {
+        self.instructions.values().sum()
+    }
+
+    fn hits(&self, address: usize) -> u64 {
+        self.instructions.get(&address).cloned().unwrap_or(0)
+    }
+
+    /// Executed blocks of the program in `memory`, hottest first. Code the control-flow graph
+    /// doesn't know about is reported one instruction per block.
+    pub fn hot_blocks(&self, memory: &[i64]) -> Vec<BlockProfile> {
+        let cfg = ControlFlowGraph::build(memory);
+        let mut blocks: Vec<BlockProfile> = cfg
+            .blocks
+            .values()
+            .map(|block| BlockProfile {
+                start: block.start,
+                end: block.end(),
+                entries: self.hits(block.start),
+                instructions: block.instructions.iter().map(|instruction| self.hits(instruction.address)).sum(),
+                listing: Some(block.instructions.clone()),
+            })
+            .filter(|block| block.instructions > 0)
+            .collect();
+        for (address, hits) in &self.instructions {
+            if cfg.instruction_at(*address).is_none() {
+                let length = decode(memory, *address).map_or(1, |instruction| instruction.len());
+                blocks.push(BlockProfile {
+                    start: *address,
+                    end: address + length,
+                    entries: *hits,
+                    instructions: *hits,
+                    listing: None,
+                });
+            }
+        }
+        blocks.sort_by(|a, b| b.instructions.cmp(&a.instructions).then(a.start.cmp(&b.start)));
+        blocks
+    }
+
+    /// The `top` hottest blocks with their listing and per-instruction counts, then the opcode mix
+    pub fn report(&self, memory: &[i64], top: usize) -> String {
+        let total = self.total_instructions();
+        let percent = |count: u64| 100.0 * count as f64 / total.max(1) as f64;
+        let mut report = String::new();
+        writeln!(report, "{} instructions executed", total).unwrap();
+        for block in self.hot_blocks(memory).iter().take(top) {
+            writeln!(
+                report,
+                "L{}..{}: {} instructions ({:.1}%), entered {} times",
+                block.start,
+                block.end,
+                block.instructions,
+                percent(block.instructions),
+                block.entries
+            )
+            .unwrap();
+            match &block.listing {
+                Some(listing) => {
+                    for instruction in listing {
+                        let hits = self.hits(instruction.address);
+                        writeln!(report, "    {:>6} x{:<8} {}", instruction.address, hits, instruction).unwrap();
+                    }
+                }
+                None => writeln!(report, "    {:>6} x{:<8} (not in listing)", block.start, block.entries).unwrap(),
+            }
+        }
+        writeln!(report, "opcodes:").unwrap();
+        let mut opcodes: Vec<(&Opcode, &u64)> = self.opcodes.iter().collect();
+        opcodes.sort_by(|a, b| b.1.cmp(a.1));
+        for (opcode, count) in opcodes {
+            writeln!(report, "    {:<4} {:>10} ({:.1}%)", opcode.mnemonic(), count, percent(*count)).unwrap();
+        }
+        report
+    }
+
+    /// Folded stacks for flamegraph tools, one `block;instruction count` line per executed address
+    pub fn folded_stacks(&self, memory: &[i64]) -> String {
+        let mut folded = String::new();
+        let mut blocks = self.hot_blocks(memory);
+        blocks.sort_by_key(|block| block.start);
+        for block in blocks {
+            for (address, hits) in self.instructions.range(block.start..block.end) {
+                let mnemonic = decode(memory, *address).map_or("?", |instruction| instruction.opcode.mnemonic());
+                writeln!(folded, "L{};{} {} {}", block.start, address, mnemonic, hits).unwrap();
+            }
+        }
+        folded
+    }
+}
+
+/// Runs the program to completion with profiling on
+pub fn profile_program(program: &[i64], inputs: &[i64]) -> Result<Profile, IntcodeError> {
+    let mut computer = IntcodeComputer::new(program.to_vec());
+    computer.set_profiling(true);
+    computer.provide_input_iter(inputs.iter().cloned());
+    computer.run_until_halt()?;
+    Ok(computer.take_profile().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::infinite_memory_intcomputer::load_input;
+    use crate::intcode_assembler::assemble;
+
+    fn countdown() -> Vec<i64> {
+        assemble(
+            "
+                IN -> [n]
+            loop:
+                ADD [n], #-1 -> [n]
+                JNZ [n], #loop
+                OUT [n]
+                HLT
+            n: DATA 0
+            ",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn counts_instructions_and_accesses() {
+        let profile = profile_program(&countdown(), &[5]).unwrap();
+        let counts: Vec<(usize, u64)> = profile.instructions.iter().map(|(a, c)| (*a, *c)).collect();
+        assert_eq!(counts, vec![(0, 1), (2, 5), (6, 5), (9, 1)]);
+        assert_eq!(profile.opcodes[&Opcode::Add], 5);
+        assert!(!profile.opcodes.contains_key(&Opcode::Hlt));
+        assert_eq!(profile.reads[&12], 11);
+        assert_eq!(profile.writes[&12], 6);
+        assert_eq!(profile.total_instructions(), 12);
+    }
+
+    #[test]
+    fn hot_blocks_and_exports() {
+        let program = countdown();
+        let profile = profile_program(&program, &[5]).unwrap();
+        let blocks = profile.hot_blocks(&program);
+        assert_eq!((blocks[0].start, blocks[0].end, blocks[0].entries, blocks[0].instructions), (2, 9, 5, 10));
+        let report = profile.report(&program, 1);
+        assert!(report.starts_with("12 instructions executed\nL2..9: 10 instructions (83.3%), entered 5 times\n"));
+        assert!(report.contains("x5        ADD [12], #-1 -> [12]"));
+        assert!(!report.contains("OUT [12]"));
+        assert_eq!(
+            profile.folded_stacks(&program),
+            "L0;0 IN 1\nL2;2 ADD 5\nL2;6 JNZ 5\nL9;9 OUT 1\n"
+        );
+    }
+
+    #[test]
+    fn profiling_does_not_change_results() {
+        let program = load_input("input/day_nine.txt").unwrap();
+        let mut computer = IntcodeComputer::new(program.clone());
+        computer.set_profiling(true);
+        computer.provide_input(1);
+        let output = computer.run_until_halt().unwrap();
+        assert_eq!(output, vec![3100786347]);
+        let profile = computer.take_profile().unwrap();
+        assert_eq!(profile.total_instructions(), computer.instructions_executed());
+        assert!(computer.profile().unwrap().instructions.is_empty());
+        assert!(!profile.hot_blocks(&program).is_empty());
+    }
+}
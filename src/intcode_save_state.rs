@@ -0,0 +1,309 @@
+use crate::checksum::fnv1a;
+use crate::infinite_memory_intcomputer::{IntcodeSnapshot, MachineState};
+use crate::intcode_memory::{Memory, Page, PAGE_SIZE};
+use std::collections::{HashSet, VecDeque};
+use std::convert::TryInto;
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Read, Write};
+
+// Layout, all integers little endian:
+//   magic "INTC", u32 version
+//   i32 instruction pointer, i32 relative base, u64 instructions executed
+//   u64 memory length, u64 page count, per page u64 first address and PAGE_SIZE i64 values
+//   input queue, output and output queue as u64 count followed by i64 values
+//   u64 FNV-1a checksum of everything before it
+const MAGIC: &[u8; 4] = b"INTC";
+pub const VERSION: u32 = 2;
+
+#[derive(Debug)]
+pub enum SaveStateError {
+    Io(io::Error),
+    BadMagic,
+    UnsupportedVersion { found: u32, expected: u32 },
+    ChecksumMismatch,
+    Corrupt(String),
+}
+
+impl fmt::Display for SaveStateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SaveStateError::Io(error) => write!(f, "save state io error: {}", error),
+            SaveStateError::BadMagic => write!(f, "not an Intcode save state"),
+            SaveStateError::UnsupportedVersion { found, expected } => {
+                write!(f, "unsupported save state version {}, expected {}", found, expected)
+            }
+            SaveStateError::ChecksumMismatch => write!(f, "save state checksum mismatch"),
+            SaveStateError::Corrupt(reason) => write!(f, "corrupt save state: {}", reason),
+        }
+    }
+}
+
+impl Error for SaveStateError {}
+
+impl From<io::Error> for SaveStateError {
+    fn from(error: io::Error) -> SaveStateError {
+        SaveStateError::Io(error)
+    }
+}
+
+fn put_values<'a, I: IntoIterator<Item = &'a i64>>(buffer: &mut Vec<u8>, count: usize, values: I) {
+    buffer.extend_from_slice(&(count as u64).to_le_bytes());
+    for value in values {
+        buffer.extend_from_slice(&value.to_le_bytes());
+    }
+}
+
+pub(crate) fn write_snapshot<W: Write>(snapshot: &IntcodeSnapshot, mut writer: W) -> Result<(), SaveStateError> {
+    let state = snapshot.state();
+    let mut buffer = MAGIC.to_vec();
+    buffer.extend_from_slice(&VERSION.to_le_bytes());
+    buffer.extend_from_slice(&state.instruction_pointer.to_le_bytes());
+    buffer.extend_from_slice(&state.relative_base.to_le_bytes());
+    buffer.extend_from_slice(&state.instructions_executed.to_le_bytes());
+    buffer.extend_from_slice(&(state.memory.len() as u64).to_le_bytes());
+    let pages = state.memory.pages();
+    buffer.extend_from_slice(&(pages.len() as u64).to_le_bytes());
+    for (address, page) in pages {
+        buffer.extend_from_slice(&(address as u64).to_le_bytes());
+        for value in page.iter() {
+            buffer.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+    put_values(&mut buffer, state.input_queue.len(), &state.input_queue);
+    put_values(&mut buffer, state.output.len(), &state.output);
+    put_values(&mut buffer, state.output_queue.len(), &state.output_queue);
+    let checksum = fnv1a(&buffer);
+    buffer.extend_from_slice(&checksum.to_le_bytes());
+    writer.write_all(&buffer)?;
+    Ok(())
+}
+
+struct Cursor<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Cursor<'a> {
+    fn take(&mut self, count: usize) -> Result<&'a [u8], SaveStateError> {
+        if self.bytes.len() < count {
+            return Err(SaveStateError::Corrupt("unexpected end of data".to_owned()));
+        }
+        let (taken, rest) = self.bytes.split_at(count);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn u64(&mut self) -> Result<u64, SaveStateError> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn i64(&mut self) -> Result<i64, SaveStateError> {
+        Ok(i64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn i32(&mut self) -> Result<i32, SaveStateError> {
+        Ok(i32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    /// Reads a count and checks it against the bytes left so garbage can't trigger huge allocations
+    fn count(&mut self, item_size: usize) -> Result<usize, SaveStateError> {
+        let count = self.u64()?;
+        if count > (self.bytes.len() / item_size) as u64 {
+            return Err(SaveStateError::Corrupt(format!("count {} exceeds remaining data", count)));
+        }
+        Ok(count as usize)
+    }
+
+    fn values(&mut self) -> Result<Vec<i64>, SaveStateError> {
+        let count = self.count(8)?;
+        (0..count).map(|_| self.i64()).collect()
+    }
+}
+
+pub(crate) fn read_snapshot<R: Read>(mut reader: R) -> Result<IntcodeSnapshot, SaveStateError> {
+    let mut bytes = vec![];
+    reader.read_to_end(&mut bytes)?;
+    if bytes.len() < 8 || &bytes[..4] != MAGIC {
+        return Err(SaveStateError::BadMagic);
+    }
+    let version = u32::from_le_bytes(bytes[4..8].try_into().unwrap());
+    if version != VERSION {
+        return Err(SaveStateError::UnsupportedVersion {
+            found: version,
+            expected: VERSION,
+        });
+    }
+    if bytes.len() < 16 {
+        return Err(SaveStateError::Corrupt("unexpected end of data".to_owned()));
+    }
+    let (body, checksum) = bytes.split_at(bytes.len() - 8);
+    if fnv1a(body) != u64::from_le_bytes(checksum.try_into().unwrap()) {
+        return Err(SaveStateError::ChecksumMismatch);
+    }
+
+    let mut cursor = Cursor { bytes: &body[8..] };
+    let instruction_pointer = cursor.i32()?;
+    let relative_base = cursor.i32()?;
+    let instructions_executed = cursor.u64()?;
+    let memory_len = cursor.u64()? as usize;
+    let page_count = cursor.count(8 * (PAGE_SIZE + 1))?;
+    let mut pages = Vec::with_capacity(page_count);
+    let mut addresses = HashSet::new();
+    for _ in 0..page_count {
+        let address = cursor.u64()? as usize;
+        if !addresses.insert(address) {
+            return Err(SaveStateError::Corrupt(format!("memory page at {} saved twice", address)));
+        }
+        let mut page: Page = [0; PAGE_SIZE];
+        for value in page.iter_mut() {
+            *value = cursor.i64()?;
+        }
+        pages.push((address, page));
+    }
+    // writes allocate their page, so nothing past the last page can be part of the memory
+    let limit = pages.iter().map(|(address, _)| address.saturating_add(PAGE_SIZE)).max().unwrap_or(0);
+    if memory_len > limit {
+        return Err(SaveStateError::Corrupt(format!(
+            "memory length {} past the last page ending at {}",
+            memory_len, limit
+        )));
+    }
+    let memory = Memory::from_pages(pages, memory_len)
+        .ok_or_else(|| SaveStateError::Corrupt("misaligned memory page".to_owned()))?;
+    let input_queue: VecDeque<i64> = cursor.values()?.into();
+    let output = cursor.values()?;
+    let output_queue: VecDeque<i64> = cursor.values()?.into();
+    if !cursor.bytes.is_empty() {
+        return Err(SaveStateError::Corrupt(format!("{} trailing bytes", cursor.bytes.len())));
+    }
+    Ok(IntcodeSnapshot::from_state(MachineState {
+        memory,
+        instruction_pointer,
+        relative_base,
+        input_queue,
+        output,
+        output_queue,
+        instructions_executed,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::infinite_memory_intcomputer::{IntcodeComputer, IntcodeComputerState, ParamMode};
+    use std::fs::File;
+
+    fn saved(computer: &IntcodeComputer) -> Vec<u8> {
+        let mut bytes = vec![];
+        computer.save_to(&mut bytes).unwrap();
+        bytes
+    }
+
+    fn paused_robot() -> IntcodeComputer {
+        let mut computer = IntcodeComputer::new_from_file("input/day_eleven.txt").unwrap();
+        computer.provide_input(0);
+        let mut outputs = 0;
+        while outputs < 10 {
+            match computer.run().unwrap() {
+                IntcodeComputerState::OutputProduced(_) => outputs += 1,
+                IntcodeComputerState::WaitingForInput => computer.provide_input(1),
+                _ => break,
+            }
+        }
+        computer.provide_input(1);
+        computer.write_memory(50_000, 7, ParamMode::PositionMode).unwrap();
+        computer
+    }
+
+    #[test]
+    fn round_trip_preserves_state() {
+        let mut original = paused_robot();
+        let mut loaded = IntcodeComputer::load_from(&saved(&original)[..]).unwrap();
+        assert_eq!(loaded.instruction_pointer(), original.instruction_pointer());
+        assert_eq!(loaded.relative_base(), original.relative_base());
+        assert_eq!(loaded.instructions_executed(), original.instructions_executed());
+        assert_eq!(loaded.input_queue(), original.input_queue());
+        assert_eq!(loaded.get_output(), original.get_output());
+        assert_eq!(loaded.dump_memory(), original.dump_memory());
+        for _ in 0..20 {
+            assert_eq!(loaded.run().unwrap(), original.run().unwrap());
+            original.provide_input(0);
+            loaded.provide_input(0);
+        }
+    }
+
+    #[test]
+    fn round_trip_through_file() {
+        let original = paused_robot();
+        let path = std::env::temp_dir().join(format!("intcode_save_state_{}.bin", std::process::id()));
+        original.save_to(File::create(&path).unwrap()).unwrap();
+        let loaded = IntcodeComputer::load_from(File::open(&path).unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(saved(&loaded), saved(&original));
+    }
+
+    #[test]
+    fn rejects_wrong_version_and_magic() {
+        let mut bytes = saved(&IntcodeComputer::new(vec![99]));
+        bytes[4..8].copy_from_slice(&7_u32.to_le_bytes());
+        match IntcodeComputer::load_from(&bytes[..]) {
+            Err(SaveStateError::UnsupportedVersion { found: 7, expected: VERSION }) => (),
+            other => panic!("unexpected {:?}", other.map(|_| ())),
+        }
+        bytes[4..8].copy_from_slice(&1_u32.to_le_bytes());
+        match IntcodeComputer::load_from(&bytes[..]) {
+            Err(SaveStateError::UnsupportedVersion { found: 1, expected: VERSION }) => (),
+            other => panic!("unexpected {:?}", other.map(|_| ())),
+        }
+        match IntcodeComputer::load_from(&b"PNG\0\x01\0\0\0"[..]) {
+            Err(SaveStateError::BadMagic) => (),
+            other => panic!("unexpected {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn rejects_corrupt_data() {
+        let bytes = saved(&IntcodeComputer::new(vec![1, 0, 0, 0, 99]));
+        let mut flipped = bytes.clone();
+        flipped[20] ^= 1;
+        match IntcodeComputer::load_from(&flipped[..]) {
+            Err(SaveStateError::ChecksumMismatch) => (),
+            other => panic!("unexpected {:?}", other.map(|_| ())),
+        }
+
+        // valid checksum over a truncated body
+        let mut truncated = bytes[..bytes.len() - 16].to_vec();
+        let checksum = fnv1a(&truncated);
+        truncated.extend_from_slice(&checksum.to_le_bytes());
+        match IntcodeComputer::load_from(&truncated[..]) {
+            Err(SaveStateError::Corrupt(_)) => (),
+            other => panic!("unexpected {:?}", other.map(|_| ())),
+        }
+
+        let with_memory_len = |memory_len: usize| {
+            let mut bytes = bytes.clone();
+            bytes[24..32].copy_from_slice(&(memory_len as u64).to_le_bytes());
+            let length = bytes.len() - 8;
+            let checksum = fnv1a(&bytes[..length]);
+            bytes[length..].copy_from_slice(&checksum.to_le_bytes());
+            bytes
+        };
+        assert_eq!(IntcodeComputer::load_from(&with_memory_len(PAGE_SIZE)[..]).unwrap().dump_memory().len(), PAGE_SIZE);
+        match IntcodeComputer::load_from(&with_memory_len(PAGE_SIZE + 1)[..]) {
+            Err(SaveStateError::Corrupt(_)) => (),
+            other => panic!("unexpected {:?}", other.map(|_| ())),
+        }
+
+        // the one page twice, the second copy would silently replace the first
+        let mut duplicated = bytes[..bytes.len() - 8].to_vec();
+        let page = duplicated[40..48 + 8 * PAGE_SIZE].to_vec();
+        duplicated.splice(40..40, page);
+        duplicated[32..40].copy_from_slice(&2_u64.to_le_bytes());
+        let checksum = fnv1a(&duplicated);
+        duplicated.extend_from_slice(&checksum.to_le_bytes());
+        match IntcodeComputer::load_from(&duplicated[..]) {
+            Err(SaveStateError::Corrupt(message)) => assert_eq!(message, "memory page at 0 saved twice"),
+            other => panic!("unexpected {:?}", other.map(|_| ())),
+        }
+    }
+}
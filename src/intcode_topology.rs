@@ -0,0 +1,262 @@
+use crate::infinite_memory_intcomputer::{IntcodeComputer, IntcodeComputerState, IntcodeError};
+use itertools::Itertools;
+use std::error::Error;
+use std::fmt;
+use std::thread;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NodeId(usize);
+
+/// Best phase assignment and the signal it produced
+pub type PhaseSearch = Option<(Vec<i64>, i64)>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TopologyError {
+    Intcode { node: NodeId, error: IntcodeError },
+    /// Every node that hasn't halted waits for input nobody will send
+    Deadlock { waiting: Vec<NodeId> },
+    UnknownNode(NodeId),
+    NoResult(NodeId),
+}
+
+impl fmt::Display for TopologyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TopologyError::Intcode { node, error } => write!(f, "node {}: {}", node.0, error),
+            TopologyError::Deadlock { waiting } => {
+                let nodes: Vec<String> = waiting.iter().map(|node| node.0.to_string()).collect();
+                write!(f, "deadlock, nodes {} wait for input", nodes.join(", "))
+            }
+            TopologyError::UnknownNode(node) => write!(f, "unknown node {}", node.0),
+            TopologyError::NoResult(node) => write!(f, "node {} produced no output", node.0),
+        }
+    }
+}
+
+impl Error for TopologyError {}
+
+#[derive(Debug, Clone)]
+struct Node {
+    program: Vec<i64>,
+    phase: Option<i64>,
+    inputs: Vec<i64>,
+    successors: Vec<NodeId>,
+}
+
+/// Directed graph of Intcode machines, every output of a node is sent to all its successors
+#[derive(Debug, Clone, Default)]
+pub struct Topology {
+    nodes: Vec<Node>,
+}
+
+impl Topology {
+    pub fn new() -> Topology {
+        Topology::default()
+    }
+
+    pub fn add_node(&mut self, program: Vec<i64>) -> NodeId {
+        self.nodes.push(Node {
+            program,
+            phase: None,
+            inputs: vec![],
+            successors: vec![],
+        });
+        NodeId(self.nodes.len() - 1)
+    }
+
+    fn node_mut(&mut self, node: NodeId) -> Result<&mut Node, TopologyError> {
+        self.nodes.get_mut(node.0).ok_or(TopologyError::UnknownNode(node))
+    }
+
+    /// The phase is the first input a node reads
+    pub fn set_phase(&mut self, node: NodeId, phase: i64) -> Result<(), TopologyError> {
+        self.node_mut(node)?.phase = Some(phase);
+        Ok(())
+    }
+
+    /// Queues an input after the phase, before anything the node's predecessors send
+    pub fn add_input(&mut self, node: NodeId, value: i64) -> Result<(), TopologyError> {
+        self.node_mut(node)?.inputs.push(value);
+        Ok(())
+    }
+
+    pub fn connect(&mut self, from: NodeId, to: NodeId) -> Result<(), TopologyError> {
+        self.node_mut(to)?;
+        self.node_mut(from)?.successors.push(to);
+        Ok(())
+    }
+
+    /// Runs all nodes until every one halted and returns the outputs of each sink.
+    ///
+    /// Nodes take turns in the order they were added, each running until it needs input or halts.
+    pub fn run(&self, sinks: &[NodeId]) -> Result<Vec<Vec<i64>>, TopologyError> {
+        if let Some(sink) = sinks.iter().find(|sink| sink.0 >= self.nodes.len()) {
+            return Err(TopologyError::UnknownNode(*sink));
+        }
+        let mut computers: Vec<IntcodeComputer> = self
+            .nodes
+            .iter()
+            .map(|node| {
+                let mut computer = IntcodeComputer::new(node.program.clone());
+                computer.provide_input_iter(node.phase.into_iter().chain(node.inputs.iter().cloned()));
+                computer
+            })
+            .collect();
+        let mut outputs = vec![vec![]; self.nodes.len()];
+        let mut halted = vec![false; self.nodes.len()];
+        while halted.iter().any(|halted| !halted) {
+            let mut progress = false;
+            for (index, node) in self.nodes.iter().enumerate() {
+                if halted[index] {
+                    continue;
+                }
+                let computer = &mut computers[index];
+                let executed = computer.instructions_executed();
+                let state = computer
+                    .run_ignore_output()
+                    .map_err(|error| TopologyError::Intcode { node: NodeId(index), error })?;
+                progress |= computer.instructions_executed() != executed;
+                halted[index] = state == IntcodeComputerState::Halted;
+                let produced = computer.pop_output();
+                for successor in &node.successors {
+                    computers[successor.0].provide_input_iter(produced.iter().cloned());
+                }
+                outputs[index].extend(produced);
+            }
+            if !progress && halted.iter().any(|halted| !halted) {
+                let waiting = (0..self.nodes.len()).filter(|index| !halted[*index]).map(NodeId).collect();
+                return Err(TopologyError::Deadlock { waiting });
+            }
+        }
+        Ok(sinks.iter().map(|sink| outputs[sink.0].clone()).collect())
+    }
+
+    /// Last output of `sink`, the usual signal value
+    pub fn signal(&self, sink: NodeId) -> Result<i64, TopologyError> {
+        let outputs = self.run(&[sink])?;
+        outputs[0].last().cloned().ok_or(TopologyError::NoResult(sink))
+    }
+
+    /// Tries every assignment of `phases` to `nodes` and returns the one with the highest
+    /// signal at `sink`. The permutations are spread over all available cores.
+    pub fn search_phases(
+        &self,
+        nodes: &[NodeId],
+        phases: &[i64],
+        sink: NodeId,
+    ) -> Result<PhaseSearch, TopologyError> {
+        let permutations: Vec<Vec<i64>> = phases.iter().cloned().permutations(nodes.len()).collect();
+        let workers = thread::available_parallelism().map_or(1, |count| count.get());
+        let chunk_size = permutations.len().div_ceil(workers);
+        let results: Vec<Result<PhaseSearch, TopologyError>> = thread::scope(|scope| {
+            let handles: Vec<_> = permutations
+                .chunks(chunk_size.max(1))
+                .map(|chunk| {
+                    scope.spawn(move || {
+                        let mut best: PhaseSearch = None;
+                        for permutation in chunk {
+                            let mut topology = self.clone();
+                            for (node, phase) in nodes.iter().zip(permutation) {
+                                topology.set_phase(*node, *phase)?;
+                            }
+                            let signal = topology.signal(sink)?;
+                            if best.as_ref().is_none_or(|(_, best)| signal > *best) {
+                                best = Some((permutation.clone(), signal));
+                            }
+                        }
+                        Ok(best)
+                    })
+                })
+                .collect();
+            handles.into_iter().map(|handle| handle.join().unwrap()).collect()
+        });
+        let mut best: PhaseSearch = None;
+        for result in results {
+            if let Some((phases, signal)) = result? {
+                if best.as_ref().is_none_or(|(_, best)| signal > *best) {
+                    best = Some((phases, signal));
+                }
+            }
+        }
+        Ok(best)
+    }
+}
+
+/// Amplifiers in a row, the first reads signal 0, the last optionally feeds back into the first
+pub fn amplifier_chain(program: &[i64], count: usize, feedback: bool) -> (Topology, Vec<NodeId>) {
+    let mut topology = Topology::new();
+    let nodes: Vec<NodeId> = (0..count).map(|_| topology.add_node(program.to_vec())).collect();
+    for pair in nodes.windows(2) {
+        topology.connect(pair[0], pair[1]).unwrap();
+    }
+    if feedback && count > 0 {
+        topology.connect(nodes[count - 1], nodes[0]).unwrap();
+    }
+    if let Some(first) = nodes.first() {
+        topology.add_input(*first, 0).unwrap();
+    }
+    (topology, nodes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode_assembler::assemble;
+
+    // adds the phase to every input, passes a 0 on and halts
+    fn adder() -> Vec<i64> {
+        assemble(
+            "
+                IN -> [phase]
+            loop:
+                IN -> [value]
+                JZ [value], #end
+                ADD [value], [phase] -> [value]
+                OUT [value]
+                JNZ #1, #loop
+            end:
+                OUT #0
+                HLT
+            phase: DATA 0
+            value: DATA 0
+            ",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn fan_out_and_fan_in() {
+        let mut topology = Topology::new();
+        let source = topology.add_node(adder());
+        let left = topology.add_node(adder());
+        let right = topology.add_node(adder());
+        let sink = topology.add_node(adder());
+        for (node, phase) in &[(source, 1), (left, 10), (right, 100), (sink, 1000)] {
+            topology.set_phase(*node, *phase).unwrap();
+        }
+        topology.add_input(source, 5).unwrap();
+        topology.add_input(source, 0).unwrap();
+        topology.connect(source, left).unwrap();
+        topology.connect(source, right).unwrap();
+        topology.connect(left, sink).unwrap();
+        topology.connect(right, sink).unwrap();
+        // the 0 from left already stops the sink before the right branch arrives
+        assert_eq!(topology.run(&[left, right, sink]).unwrap(), vec![vec![16, 0], vec![106, 0], vec![1016, 0]]);
+    }
+
+    #[test]
+    fn reports_deadlock() {
+        let (topology, nodes) = amplifier_chain(&adder(), 2, true);
+        assert_eq!(topology.signal(nodes[1]), Err(TopologyError::Deadlock { waiting: nodes.clone() }));
+        let mut topology = topology;
+        assert_eq!(topology.connect(nodes[0], NodeId(7)), Err(TopologyError::UnknownNode(NodeId(7))));
+    }
+
+    #[test]
+    fn parallel_search_finds_best_phases() {
+        let program = vec![3, 15, 3, 16, 1002, 16, 10, 16, 1, 16, 15, 15, 4, 15, 99, 0, 0];
+        let (topology, nodes) = amplifier_chain(&program, 5, false);
+        let best = topology.search_phases(&nodes, &[0, 1, 2, 3, 4], nodes[4]).unwrap();
+        assert_eq!(best, Some((vec![4, 3, 2, 1, 0], 43210)));
+    }
+}
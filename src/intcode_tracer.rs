@@ -0,0 +1,479 @@
+use crate::infinite_memory_intcomputer::{get_op_code, load_input, IntcodeComputer, IntcodeComputerState, MemoryAccess};
+use crate::intcode_disassembler::decode;
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::sync::{Arc, Mutex};
+
+/// One executed instruction as seen by a tracer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceStep {
+    /// Instructions executed before this one
+    pub index: u64,
+    pub instruction_pointer: i32,
+    /// The instruction word followed by its raw parameters
+    pub words: Vec<i64>,
+    /// Data reads and writes with resolved addresses, in execution order
+    pub accesses: Vec<MemoryAccess>,
+    pub relative_base_before: i32,
+    pub relative_base_after: i32,
+}
+
+impl TraceStep {
+    /// Disassembled form of `words`, `None` for words the disassembler doesn't accept
+    pub fn assembly(&self) -> Option<String> {
+        decode(&self.words, 0).map(|instruction| instruction.to_string())
+    }
+
+    fn input_value(&self) -> Option<i64> {
+        if get_op_code(self.words.first()?) != 3 {
+            return None;
+        }
+        self.accesses.iter().find_map(|access| match access {
+            MemoryAccess::Write { value, .. } => Some(*value),
+            MemoryAccess::Read { .. } => None,
+        })
+    }
+
+    pub fn to_json(&self) -> String {
+        let words: Vec<String> = self.words.iter().map(|word| word.to_string()).collect();
+        let accesses: Vec<String> = self
+            .accesses
+            .iter()
+            .map(|access| match access {
+                MemoryAccess::Read { address, value } => format!("[\"r\",{},{}]", address, value),
+                MemoryAccess::Write { address, value } => format!("[\"w\",{},{}]", address, value),
+            })
+            .collect();
+        format!(
+            "{{\"step\":{},\"ip\":{},\"words\":[{}],\"asm\":\"{}\",\"accesses\":[{}],\"rb\":[{},{}]}}",
+            self.index,
+            self.instruction_pointer,
+            words.join(","),
+            self.assembly().unwrap_or_default(),
+            accesses.join(","),
+            self.relative_base_before,
+            self.relative_base_after
+        )
+    }
+
+    pub fn from_json(line: &str) -> Result<TraceStep, String> {
+        let mut parser = Parser { text: line.as_bytes(), position: 0 };
+        let value = parser.value()?;
+        parser.skip_whitespace();
+        if parser.position != parser.text.len() {
+            return Err(format!("unexpected text at column {}", parser.position + 1));
+        }
+        let fields = match value {
+            Json::Object(fields) => fields,
+            _ => return Err("expected an object".to_owned()),
+        };
+        let field = |name: &str| {
+            fields
+                .iter()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value)
+                .ok_or_else(|| format!("missing field {}", name))
+        };
+        let address = |name: &str, value: i64| i32::try_from(value).map_err(|_| format!("{} {} is out of range", name, value));
+        let relative_base = field("rb")?.numbers()?;
+        if relative_base.len() != 2 {
+            return Err("rb needs a before and after value".to_owned());
+        }
+        let mut accesses = vec![];
+        for access in field("accesses")?.array()? {
+            let parts = access.array()?;
+            let (address, value) = match parts {
+                [_, location, value] => (address("access address", location.number()?)?, value.number()?),
+                _ => return Err("access needs a kind, address and value".to_owned()),
+            };
+            accesses.push(match parts[0].string()? {
+                "r" => MemoryAccess::Read { address, value },
+                "w" => MemoryAccess::Write { address, value },
+                kind => return Err(format!("unknown access kind {}", kind)),
+            });
+        }
+        let step = field("step")?.number()?;
+        Ok(TraceStep {
+            index: u64::try_from(step).map_err(|_| format!("step {} is out of range", step))?,
+            instruction_pointer: address("ip", field("ip")?.number()?)?,
+            words: field("words")?.numbers()?,
+            accesses,
+            relative_base_before: address("rb", relative_base[0])?,
+            relative_base_after: address("rb", relative_base[1])?,
+        })
+    }
+}
+
+impl fmt::Display for TraceStep {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let text = self.assembly().unwrap_or_else(|| format!("{:?}", self.words));
+        write!(f, "#{:<8} {:>6}  {:<36}", self.index, self.instruction_pointer, text)?;
+        for access in &self.accesses {
+            match access {
+                MemoryAccess::Read { address, value } => write!(f, " [{}]={}", address, value)?,
+                MemoryAccess::Write { address, value } => write!(f, " [{}]<-{}", address, value)?,
+            }
+        }
+        if self.relative_base_before != self.relative_base_after {
+            write!(f, " rb {}->{}", self.relative_base_before, self.relative_base_after)?;
+        }
+        Ok(())
+    }
+}
+
+// just enough JSON for reading traces back: integers, plain strings, arrays and objects
+enum Json {
+    Number(i64),
+    Str(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    fn number(&self) -> Result<i64, String> {
+        match self {
+            Json::Number(number) => Ok(*number),
+            _ => Err("expected a number".to_owned()),
+        }
+    }
+
+    fn string(&self) -> Result<&str, String> {
+        match self {
+            Json::Str(text) => Ok(text),
+            _ => Err("expected a string".to_owned()),
+        }
+    }
+
+    fn array(&self) -> Result<&[Json], String> {
+        match self {
+            Json::Array(values) => Ok(values),
+            _ => Err("expected an array".to_owned()),
+        }
+    }
+
+    fn numbers(&self) -> Result<Vec<i64>, String> {
+        self.array()?.iter().map(Json::number).collect()
+    }
+}
+
+struct Parser<'a> {
+    text: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn skip_whitespace(&mut self) {
+        while self.text.get(self.position).is_some_and(|c| c.is_ascii_whitespace()) {
+            self.position += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Option<u8> {
+        self.skip_whitespace();
+        self.text.get(self.position).cloned()
+    }
+
+    fn expect(&mut self, expected: u8) -> Result<(), String> {
+        if self.peek() == Some(expected) {
+            self.position += 1;
+            Ok(())
+        } else {
+            Err(format!("expected '{}' at column {}", expected as char, self.position + 1))
+        }
+    }
+
+    /// Parses comma separated items up to `close`, the opening bracket already consumed
+    fn list<T, F: FnMut(&mut Parser<'a>) -> Result<T, String>>(&mut self, close: u8, mut item: F) -> Result<Vec<T>, String> {
+        let mut items = vec![];
+        if self.peek() == Some(close) {
+            self.position += 1;
+            return Ok(items);
+        }
+        loop {
+            items.push(item(self)?);
+            if self.peek() == Some(b',') {
+                self.position += 1;
+            } else {
+                self.expect(close)?;
+                return Ok(items);
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.expect(b'"')?;
+        let start = self.position;
+        while let Some(c) = self.text.get(self.position) {
+            match c {
+                b'"' => {
+                    self.position += 1;
+                    return Ok(String::from_utf8_lossy(&self.text[start..self.position - 1]).into_owned());
+                }
+                b'\\' => return Err("escapes aren't supported".to_owned()),
+                _ => self.position += 1,
+            }
+        }
+        Err("unterminated string".to_owned())
+    }
+
+    fn value(&mut self) -> Result<Json, String> {
+        match self.peek() {
+            Some(b'{') => {
+                self.position += 1;
+                let fields = self.list(b'}', |parser| {
+                    let key = parser.string()?;
+                    parser.expect(b':')?;
+                    Ok((key, parser.value()?))
+                })?;
+                Ok(Json::Object(fields))
+            }
+            Some(b'[') => {
+                self.position += 1;
+                Ok(Json::Array(self.list(b']', Parser::value)?))
+            }
+            Some(b'"') => Ok(Json::Str(self.string()?)),
+            Some(c) if c == b'-' || c.is_ascii_digit() => {
+                let start = self.position;
+                self.position += 1;
+                while self.text.get(self.position).is_some_and(|c| c.is_ascii_digit()) {
+                    self.position += 1;
+                }
+                let text = std::str::from_utf8(&self.text[start..self.position]).unwrap();
+                text.parse().map(Json::Number).map_err(|_| format!("invalid number {}", text))
+            }
+            _ => Err(format!("unexpected input at column {}", self.position + 1)),
+        }
+    }
+}
+
+/// Receives every instruction a traced `IntcodeComputer` executes
+pub trait TraceSink {
+    fn record(&mut self, step: &TraceStep);
+}
+
+impl TraceSink for Vec<TraceStep> {
+    fn record(&mut self, step: &TraceStep) {
+        self.push(step.clone());
+    }
+}
+
+/// Writes one JSON object per executed instruction, keeping the first write error for `finish`
+pub struct JsonLinesTracer<W: Write> {
+    writer: W,
+    error: Option<io::Error>,
+}
+
+impl<W: Write> JsonLinesTracer<W> {
+    pub fn new(writer: W) -> JsonLinesTracer<W> {
+        JsonLinesTracer { writer, error: None }
+    }
+
+    pub fn finish(mut self) -> io::Result<W> {
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+impl JsonLinesTracer<BufWriter<File>> {
+    pub fn create(path: &str) -> io::Result<JsonLinesTracer<BufWriter<File>>> {
+        Ok(JsonLinesTracer::new(BufWriter::new(File::create(path)?)))
+    }
+}
+
+impl<W: Write> TraceSink for JsonLinesTracer<W> {
+    fn record(&mut self, step: &TraceStep) {
+        if self.error.is_none() {
+            if let Err(error) = writeln!(self.writer, "{}", step.to_json()) {
+                self.error = Some(error);
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum TraceError {
+    Io(io::Error),
+    Parse { line: usize, message: String },
+}
+
+impl fmt::Display for TraceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TraceError::Io(error) => write!(f, "trace io error: {}", error),
+            TraceError::Parse { line, message } => write!(f, "trace line {}: {}", line, message),
+        }
+    }
+}
+
+impl Error for TraceError {}
+
+pub fn read_trace<R: BufRead>(reader: R) -> Result<Vec<TraceStep>, TraceError> {
+    let mut steps = vec![];
+    for (index, line) in reader.lines().enumerate() {
+        let line = line.map_err(TraceError::Io)?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        steps.push(TraceStep::from_json(&line).map_err(|message| TraceError::Parse { line: index + 1, message })?);
+    }
+    Ok(steps)
+}
+
+/// The first trace entry a replay didn't reproduce
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    /// Position in the trace
+    pub position: usize,
+    pub expected: TraceStep,
+    pub actual: Option<TraceStep>,
+    pub reason: String,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "diverged at trace entry {}: {}", self.position, self.reason)?;
+        writeln!(f, "expected {}", self.expected)?;
+        match &self.actual {
+            Some(actual) => write!(f, "actual   {}", actual),
+            None => write!(f, "actual   nothing executed"),
+        }
+    }
+}
+
+/// Runs `program` on a fresh computer step by step, feeding it the inputs the trace read,
+/// and returns the first step that differs from the trace
+pub fn replay(program: &[i64], trace: &[TraceStep]) -> Option<Divergence> {
+    let recorded = Arc::new(Mutex::new(vec![]));
+    let mut computer = IntcodeComputer::new(program.to_vec());
+    computer.set_tracer(Some(recorded.clone()));
+    for (position, expected) in trace.iter().enumerate() {
+        let divergence = |actual: Option<TraceStep>, reason: &str| {
+            Some(Divergence {
+                position,
+                expected: expected.clone(),
+                actual,
+                reason: reason.to_owned(),
+            })
+        };
+        if let Some(input) = expected.input_value() {
+            computer.provide_input(input);
+        }
+        let state = match computer.step() {
+            Ok(state) => state,
+            Err(error) => return divergence(None, &error.to_string()),
+        };
+        let actual = recorded.lock().unwrap().pop();
+        match (state, actual) {
+            (Some(IntcodeComputerState::WaitingForInput), _) => return divergence(None, "computer waited for input"),
+            (_, None) => return divergence(None, "nothing was traced"),
+            (_, Some(actual)) if actual != *expected => {
+                let reason = if actual.instruction_pointer != expected.instruction_pointer {
+                    "instruction pointer differs"
+                } else if actual.words != expected.words {
+                    "instruction differs"
+                } else if actual.accesses != expected.accesses {
+                    "memory accesses differ"
+                } else {
+                    "relative base or step count differs"
+                };
+                return divergence(Some(actual), reason);
+            }
+            (_, Some(_)) => (),
+        }
+    }
+    None
+}
+
+/// Replays a JSON-lines trace against a program file and prints the outcome
+pub fn replay_file(program_path: &str, trace_path: &str) -> Result<(), Box<dyn Error>> {
+    let program = load_input(program_path)?;
+    let trace = read_trace(BufReader::new(File::open(trace_path)?))?;
+    match replay(&program, &trace) {
+        None => println!("trace matches, {} steps replayed", trace.len()),
+        Some(divergence) => println!("{}", divergence),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode_assembler::assemble;
+
+    fn traced_run(program: &[i64], inputs: &[i64]) -> Vec<TraceStep> {
+        let steps = Arc::new(Mutex::new(vec![]));
+        let mut computer = IntcodeComputer::new(program.to_vec());
+        computer.set_tracer(Some(steps.clone()));
+        computer.provide_input_iter(inputs.iter().cloned());
+        computer.run_until_halt().unwrap();
+        let steps = steps.lock().unwrap().clone();
+        steps
+    }
+
+    #[test]
+    fn records_operands_accesses_and_relative_base() {
+        let program = assemble("IN -> [a]\nARB [a]\nADD [rb+1], #4 -> [b]\nOUT [b]\nHLT\na: ZERO 1\nb: ZERO 1").unwrap();
+        let steps = traced_run(&program, &[3]);
+        assert_eq!(steps.len(), 5);
+        assert_eq!(steps[0].accesses, vec![MemoryAccess::Write { address: 11, value: 3 }]);
+        assert_eq!((steps[1].relative_base_before, steps[1].relative_base_after), (0, 3));
+        assert_eq!(steps[2].assembly().unwrap(), "ADD [rb+1], #4 -> [12]");
+        assert_eq!(
+            steps[2].accesses,
+            vec![MemoryAccess::Read { address: 4, value: 1201 }, MemoryAccess::Write { address: 12, value: 1205 }]
+        );
+        assert_eq!(steps[4].index, 4);
+    }
+
+    #[test]
+    fn json_lines_round_trip() {
+        let program = load_input("input/day_nine.txt").unwrap();
+        let mut tracer = JsonLinesTracer::new(vec![]);
+        for step in traced_run(&program, &[1]) {
+            tracer.record(&step);
+        }
+        let bytes = tracer.finish().unwrap();
+        let trace = read_trace(&bytes[..]).unwrap();
+        assert_eq!(trace, traced_run(&program, &[1]));
+        assert_eq!(replay(&program, &trace), None);
+    }
+
+    #[test]
+    fn replay_reports_first_divergence() {
+        let program = load_input("input/day_five.txt").unwrap();
+        let trace = traced_run(&program, &[5]);
+        let mut patched = program.clone();
+        // the program checks its input against 8, make that 7
+        let compare = trace.iter().find(|step| step.words[0] == 1008).unwrap();
+        patched[compare.instruction_pointer as usize + 2] = 7;
+        let divergence = replay(&patched, &trace).unwrap();
+        assert_eq!(divergence.expected, *compare);
+        assert_eq!(divergence.reason, "instruction differs");
+    }
+
+    #[test]
+    fn rejects_malformed_lines() {
+        match read_trace(&b"{\"step\":0}\n"[..]) {
+            Err(TraceError::Parse { line: 1, message }) => assert_eq!(message, "missing field rb"),
+            other => panic!("unexpected {:?}", other),
+        }
+
+        let program = load_input("input/day_nine.txt").unwrap();
+        let line = traced_run(&program, &[1])[0].to_json();
+        let corrupt = [
+            (line.replace("\"ip\":0", "\"ip\":4294967296"), "ip 4294967296 is out of range"),
+            (line.replace("\"step\":0", "\"step\":-1"), "step -1 is out of range"),
+            (line.replace("\"rb\":[0,", "\"rb\":[-2147483649,"), "rb -2147483649 is out of range"),
+        ];
+        for (text, expected) in &corrupt {
+            assert_ne!(text, &line);
+            assert_eq!(TraceStep::from_json(text).unwrap_err(), *expected);
+        }
+    }
+}
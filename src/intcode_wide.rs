@@ -0,0 +1,241 @@
+use crate::infinite_memory_intcomputer::{get_op_code, get_param_mode, IntcodeError, ParamMode};
+use std::collections::{HashMap, VecDeque};
+use std::convert::TryFrom;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WideState {
+    WaitingForInput,
+    Halted,
+    OutputProduced(i128),
+}
+
+/// `IntcodeComputer` with i128 values for programs whose numbers outgrow an i64.
+/// Overflowing an i128 is an error, addresses are still limited to i32.
+#[derive(Debug, Clone)]
+pub struct WideIntcodeComputer {
+    memory: Vec<i128>,
+    /// Written addresses past the program
+    extra_memory: HashMap<usize, i128>,
+    instruction_pointer: i32,
+    relative_base: i32,
+    input_queue: VecDeque<i128>,
+    output_queue: VecDeque<i128>,
+}
+
+impl WideIntcodeComputer {
+    pub fn new(program: Vec<i128>) -> WideIntcodeComputer {
+        WideIntcodeComputer {
+            memory: program,
+            extra_memory: HashMap::new(),
+            instruction_pointer: 0,
+            relative_base: 0,
+            input_queue: VecDeque::new(),
+            output_queue: VecDeque::new(),
+        }
+    }
+
+    pub fn from_program(program: &[i64]) -> WideIntcodeComputer {
+        WideIntcodeComputer::new(program.iter().map(|value| i128::from(*value)).collect())
+    }
+
+    pub fn provide_input(&mut self, input: i128) {
+        self.input_queue.push_back(input);
+    }
+
+    pub fn provide_input_iter<T: IntoIterator<Item = i128>>(&mut self, input: T) {
+        self.input_queue.extend(input);
+    }
+
+    pub fn pop_output(&mut self) -> Vec<i128> {
+        self.output_queue.drain(..).collect()
+    }
+
+    pub fn peek_memory(&self, address: usize) -> i128 {
+        match self.memory.get(address) {
+            Some(value) => *value,
+            None => self.extra_memory.get(&address).cloned().unwrap_or(0),
+        }
+    }
+
+    /// Memory up to the highest written address
+    pub fn dump_memory(&self) -> Vec<i128> {
+        let len = self.extra_memory.keys().map(|address| address + 1).max().unwrap_or(0).max(self.memory.len());
+        (0..len).map(|address| self.peek_memory(address)).collect()
+    }
+
+    fn address(&self, value: i128) -> Result<usize, IntcodeError> {
+        let ip = self.instruction_pointer;
+        let address = i32::try_from(value).map_err(|_| IntcodeError::AddressOutOfRange { addr: narrow(value), ip })?;
+        usize::try_from(address).map_err(|_| IntcodeError::NegativeAddress { addr: address as i64, ip })
+    }
+
+    fn write(&mut self, address: usize, value: i128) {
+        match self.memory.get_mut(address) {
+            Some(slot) => *slot = value,
+            None => {
+                self.extra_memory.insert(address, value);
+            }
+        }
+    }
+
+    fn mode(&self, op: i64, index: u32) -> Result<ParamMode, IntcodeError> {
+        get_param_mode(&op, index).ok_or(IntcodeError::InvalidParamMode {
+            mode: op / (10_i64.pow(index + 2)) % 10,
+            ip: self.instruction_pointer,
+        })
+    }
+
+    /// Where parameter `index` of the current instruction is stored
+    fn param_location(&self, index: u32) -> Result<usize, IntcodeError> {
+        self.address(self.instruction_pointer as i128 + 1 + index as i128)
+    }
+
+    fn advance(&mut self, length: i32) -> Result<(), IntcodeError> {
+        self.instruction_pointer = self.address(self.instruction_pointer as i128 + length as i128)? as i32;
+        Ok(())
+    }
+
+    /// Address parameter `index` refers to, `None` for an immediate
+    fn param_address(&self, op: i64, index: u32) -> Result<Option<usize>, IntcodeError> {
+        let word = self.peek_memory(self.param_location(index)?);
+        match self.mode(op, index)? {
+            ParamMode::ImmediateMode => Ok(None),
+            ParamMode::PositionMode => self.address(word).map(Some),
+            ParamMode::RelativeMode => self.address(word.saturating_add(self.relative_base as i128)).map(Some),
+        }
+    }
+
+    fn load(&self, op: i64, index: u32) -> Result<i128, IntcodeError> {
+        Ok(match self.param_address(op, index)? {
+            Some(address) => self.peek_memory(address),
+            None => self.peek_memory(self.param_location(index)?),
+        })
+    }
+
+    fn store(&mut self, op: i64, index: u32, value: i128) -> Result<(), IntcodeError> {
+        match self.param_address(op, index)? {
+            Some(address) => {
+                self.write(address, value);
+                Ok(())
+            }
+            None => Err(IntcodeError::ImmediateWrite { ip: self.instruction_pointer }),
+        }
+    }
+
+    pub fn run(&mut self) -> Result<WideState, IntcodeError> {
+        loop {
+            let ip = self.instruction_pointer;
+            let word = self.peek_memory(self.address(ip as i128)?);
+            let op = i64::try_from(word).map_err(|_| IntcodeError::UnknownOpcode { op: narrow(word), ip })?;
+            let overflow = IntcodeError::Overflow { ip };
+            match get_op_code(&op) {
+                1 => {
+                    let sum = self.load(op, 0)?.checked_add(self.load(op, 1)?).ok_or(overflow)?;
+                    self.store(op, 2, sum)?;
+                    self.advance(4)?;
+                }
+                2 => {
+                    let product = self.load(op, 0)?.checked_mul(self.load(op, 1)?).ok_or(overflow)?;
+                    self.store(op, 2, product)?;
+                    self.advance(4)?;
+                }
+                3 => match self.input_queue.pop_front() {
+                    Some(value) => {
+                        self.store(op, 0, value)?;
+                        self.advance(2)?;
+                    }
+                    None => return Ok(WideState::WaitingForInput),
+                },
+                4 => {
+                    let value = self.load(op, 0)?;
+                    self.advance(2)?;
+                    self.output_queue.push_back(value);
+                    return Ok(WideState::OutputProduced(value));
+                }
+                5 | 6 => {
+                    let condition = self.load(op, 0)?;
+                    let target = self.load(op, 1)?;
+                    if (condition != 0) == (get_op_code(&op) == 5) {
+                        self.instruction_pointer = self.address(target)? as i32;
+                    } else {
+                        self.advance(3)?;
+                    }
+                }
+                7 => {
+                    let less = self.load(op, 0)? < self.load(op, 1)?;
+                    self.store(op, 2, less as i128)?;
+                    self.advance(4)?;
+                }
+                8 => {
+                    let equal = self.load(op, 0)? == self.load(op, 1)?;
+                    self.store(op, 2, equal as i128)?;
+                    self.advance(4)?;
+                }
+                9 => {
+                    let base = self.load(op, 0)?.saturating_add(self.relative_base as i128);
+                    self.relative_base = i32::try_from(base)
+                        .map_err(|_| IntcodeError::AddressOutOfRange { addr: narrow(base), ip })?;
+                    self.advance(2)?;
+                }
+                99 => return Ok(WideState::Halted),
+                _ => return Err(IntcodeError::UnknownOpcode { op, ip }),
+            }
+        }
+    }
+
+    pub fn run_until_halt(&mut self) -> Result<Vec<i128>, IntcodeError> {
+        loop {
+            match self.run()? {
+                WideState::Halted => return Ok(self.pop_output()),
+                WideState::OutputProduced(_) => (),
+                WideState::WaitingForInput => return Err(IntcodeError::InputExhausted),
+            }
+        }
+    }
+}
+
+/// Closest i64 for error messages
+fn narrow(value: i128) -> i64 {
+    value.clamp(i64::MIN as i128, i64::MAX as i128) as i64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::infinite_memory_intcomputer::load_input;
+
+    #[test]
+    fn values_beyond_i64() {
+        // MUL [11], #4 -> [11], ADD [11], [11] -> [11], OUT [11]
+        let program = vec![1002, 11, 4, 11, 1, 11, 11, 11, 4, 11, 99, i64::MAX];
+        let mut computer = WideIntcodeComputer::from_program(&program);
+        assert_eq!(computer.run_until_halt().unwrap(), vec![i64::MAX as i128 * 8]);
+
+        let mut computer = WideIntcodeComputer::new(vec![1002, 5, 2, 5, 99, i128::MAX]);
+        assert_eq!(computer.run_until_halt().unwrap_err(), IntcodeError::Overflow { ip: 0 });
+    }
+
+    #[test]
+    fn instruction_pointer_stays_addressable() {
+        let last = i32::MAX as i128 - 1;
+        let mut computer = WideIntcodeComputer::new(vec![1101, 4, 0, last, 1105, 1, last]);
+        let err = computer.run().unwrap_err();
+        assert_eq!(err, IntcodeError::AddressOutOfRange { addr: i32::MAX as i64 + 1, ip: last as i32 });
+        let mut computer = WideIntcodeComputer::new(vec![1101, 1, 0, last + 1, 1105, 1, last + 1]);
+        let err = computer.run().unwrap_err();
+        assert_eq!(err, IntcodeError::AddressOutOfRange { addr: i32::MAX as i64 + 1, ip: i32::MAX });
+    }
+
+    #[test]
+    fn runs_day_nine() {
+        let program = load_input("input/day_nine.txt").unwrap();
+        let mut computer = WideIntcodeComputer::from_program(&program);
+        computer.provide_input(1);
+        assert_eq!(computer.run_until_halt().unwrap(), vec![3100786347]);
+
+        let quine = vec![109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99];
+        let mut computer = WideIntcodeComputer::from_program(&quine);
+        let output = computer.run_until_halt().unwrap();
+        assert_eq!(output, quine.iter().map(|value| *value as i128).collect::<Vec<i128>>());
+    }
+}
@@ -18,9 +18,32 @@ mod day_fifteen;
 mod day_seventeen;
 mod day_nineteen;
 mod day_twenty;
-
-use day_twenty::*;
+mod intcode_debugger;
+mod intcode_disassembler;
+mod intcode_assembler;
+mod intcode_memory;
+mod intcode_benchmarks;
+mod intcode_save_state;
+mod intcode_tracer;
+mod intcode_io;
+mod intcode_network;
+mod intcode_topology;
+mod intcode_ascii;
+mod intcode_cfg;
+mod intcode_profiler;
+mod intcode_fuzz;
+mod intcode_wide;
+#[cfg(feature = "async")]
+mod intcode_async;
+mod puzzle_input;
+mod input_store;
+mod solution;
+mod solutions;
+mod cli;
+mod answers;
+mod checksum;
 
 fn main() {
-    two();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    std::process::exit(cli::main(&args));
 }
@@ -0,0 +1,113 @@
+use crate::solution::SolveError;
+use std::env;
+use std::fs;
+use std::path::Path;
+
+/// Directory to read `day_<n>.txt` files from instead of the embedded inputs
+pub const INPUT_DIR_VAR: &str = "AOC_INPUT_DIR";
+
+const DAY_NAMES: [&str; 25] = [
+    "one", "two", "three", "four", "five", "six", "seven", "eight", "nine", "ten", "eleven", "twelve", "thirteen",
+    "fourteen", "fifteen", "sixteen", "seventeen", "eighteen", "nineteen", "twenty", "twenty_one", "twenty_two",
+    "twenty_three", "twenty_four", "twenty_five",
+];
+
+/// The day's file name, `day_eleven.txt` for 11
+pub fn file_name(day: u32) -> Option<String> {
+    let index = (day as usize).checked_sub(1)?;
+    DAY_NAMES.get(index).map(|name| format!("day_{}.txt", name))
+}
+
+/// The contents of `input/` when the binary was built
+pub fn embedded(day: u32) -> Option<&'static str> {
+    match day {
+        1 => Some(include_str!("../input/day_one.txt")),
+        2 => Some(include_str!("../input/day_two.txt")),
+        3 => Some(include_str!("../input/day_three.txt")),
+        4 => Some(include_str!("../input/day_four.txt")),
+        5 => Some(include_str!("../input/day_five.txt")),
+        6 => Some(include_str!("../input/day_six.txt")),
+        7 => Some(include_str!("../input/day_seven.txt")),
+        8 => Some(include_str!("../input/day_eight.txt")),
+        9 => Some(include_str!("../input/day_nine.txt")),
+        11 => Some(include_str!("../input/day_eleven.txt")),
+        12 => Some(include_str!("../input/day_twelve.txt")),
+        13 => Some(include_str!("../input/day_thirteen.txt")),
+        15 => Some(include_str!("../input/day_fifteen.txt")),
+        17 => Some(include_str!("../input/day_seventeen.txt")),
+        19 => Some(include_str!("../input/day_nineteen.txt")),
+        20 => Some(include_str!("../input/day_twenty.txt")),
+        _ => None,
+    }
+}
+
+pub fn read_input(path: &str) -> Result<String, SolveError> {
+    fs::read_to_string(path).map_err(|e| SolveError::Io {
+        path: path.to_owned(),
+        message: e.to_string(),
+    })
+}
+
+/// Where solvers get their puzzle input from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InputSource {
+    /// This file for whichever day asks
+    Path(String),
+    /// The day's file in this directory
+    Dir(String),
+    Embedded,
+    Memory(String),
+}
+
+impl InputSource {
+    /// `AOC_INPUT_DIR` when it is set, the embedded inputs otherwise
+    pub fn from_env() -> InputSource {
+        match env::var(INPUT_DIR_VAR) {
+            Ok(dir) => InputSource::Dir(dir),
+            Err(_) => InputSource::Embedded,
+        }
+    }
+
+    /// `NoInput` for missing days and empty inputs
+    pub fn read(&self, day: u32) -> Result<String, SolveError> {
+        let input = match self {
+            InputSource::Path(path) => read_input(path)?,
+            InputSource::Dir(dir) => {
+                let name = file_name(day).ok_or(SolveError::NoInput(day))?;
+                let path = Path::new(dir).join(name);
+                if !path.exists() {
+                    return Err(SolveError::NoInput(day));
+                }
+                read_input(&path.to_string_lossy())?
+            }
+            InputSource::Embedded => embedded(day).ok_or(SolveError::NoInput(day))?.to_owned(),
+            InputSource::Memory(input) => input.clone(),
+        };
+        if input.trim().is_empty() {
+            return Err(SolveError::NoInput(day));
+        }
+        Ok(input)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sources_agree() {
+        assert_eq!(file_name(11).unwrap(), "day_eleven.txt");
+        assert_eq!(file_name(0), None);
+        let embedded = InputSource::Embedded.read(6).unwrap();
+        assert_eq!(InputSource::Dir("input".to_owned()).read(6).unwrap(), embedded);
+        assert_eq!(InputSource::Path("input/day_six.txt".to_owned()).read(1).unwrap(), embedded);
+        assert_eq!(InputSource::Memory("A)B".to_owned()).read(6).unwrap(), "A)B");
+        assert_eq!(InputSource::Embedded.read(10), Err(SolveError::NoInput(10)));
+        assert_eq!(InputSource::Dir("nowhere".to_owned()).read(6), Err(SolveError::NoInput(6)));
+        assert_eq!(InputSource::Memory("\n".to_owned()).read(6), Err(SolveError::NoInput(6)));
+        match InputSource::Path("nowhere/day_six.txt".to_owned()).read(6) {
+            Err(SolveError::Io { path, .. }) => assert_eq!(path, "nowhere/day_six.txt"),
+            result => panic!("expected an io error, got {:?}", result),
+        }
+    }
+}
@@ -0,0 +1,93 @@
+use crate::infinite_memory_intcomputer::IntcodeError;
+use std::error::Error;
+use std::fmt;
+
+/// A puzzle answer as the website expects it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Answer {
+    Number(i64),
+    Text(String),
+    /// Pixels to read letters off, one row per line
+    Image(Vec<Vec<bool>>),
+}
+
+impl fmt::Display for Answer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Answer::Number(number) => write!(f, "{}", number),
+            Answer::Text(text) => write!(f, "{}", text),
+            Answer::Image(rows) => {
+                for row in rows {
+                    let line: String = row.iter().map(|lit| if *lit { '#' } else { ' ' }).collect();
+                    writeln!(f, "{}", line.trim_end())?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SolveError {
+    Io { path: String, message: String },
+    NoInput(u32),
+    Parse(String),
+    Intcode(IntcodeError),
+    NoAnswer(String),
+    Unsolved,
+}
+
+impl fmt::Display for SolveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SolveError::Io { path, message } => write!(f, "Failed reading {}: {}", path, message),
+            SolveError::NoInput(day) => write!(f, "Input for day {} is not available", day),
+            SolveError::Parse(message) => write!(f, "Invalid input: {}", message),
+            SolveError::Intcode(error) => write!(f, "Intcode error: {}", error),
+            SolveError::NoAnswer(message) => write!(f, "No answer: {}", message),
+            SolveError::Unsolved => write!(f, "Not solved yet"),
+        }
+    }
+}
+
+impl Error for SolveError {}
+
+impl From<IntcodeError> for SolveError {
+    fn from(error: IntcodeError) -> SolveError {
+        SolveError::Intcode(error)
+    }
+}
+
+/// For the days whose helpers still return boxed errors
+impl From<Box<dyn Error>> for SolveError {
+    fn from(error: Box<dyn Error>) -> SolveError {
+        match error.downcast::<IntcodeError>() {
+            Ok(error) => SolveError::Intcode(*error),
+            Err(error) => SolveError::NoAnswer(error.to_string()),
+        }
+    }
+}
+
+/// Both parts of a day, taking the puzzle input as text
+pub trait Solution {
+    fn part1(&self, input: &str) -> Result<Answer, SolveError>;
+    fn part2(&self, input: &str) -> Result<Answer, SolveError>;
+}
+
+pub fn parse_number<T: std::str::FromStr>(text: &str) -> Result<T, SolveError> {
+    text.trim().parse().map_err(|_| SolveError::Parse(format!("{:?} isn't a number", text)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn answers_display() {
+        assert_eq!(Answer::Number(-12).to_string(), "-12");
+        let image = Answer::Image(vec![vec![true, false, true, false], vec![false, true, false, false]]);
+        assert_eq!(image.to_string(), "# #\n #\n");
+        let error: Box<dyn Error> = Box::new(IntcodeError::InputExhausted);
+        assert_eq!(SolveError::from(error), SolveError::Intcode(IntcodeError::InputExhausted));
+    }
+}
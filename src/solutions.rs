@@ -0,0 +1,60 @@
+use crate::day_eight::DayEight;
+use crate::day_eleven::DayEleven;
+use crate::day_fifteen::DayFifteen;
+use crate::day_five::DayFive;
+use crate::day_four::DayFour;
+use crate::day_nine::DayNine;
+use crate::day_nineteen::DayNineteen;
+use crate::day_one::DayOne;
+use crate::day_seven::DaySeven;
+use crate::day_seventeen::DaySeventeen;
+use crate::day_six::DaySix;
+use crate::day_thirteen::DayThirteen;
+use crate::day_three::DayThree;
+use crate::day_twelve::DayTwelve;
+use crate::day_twenty::DayTwenty;
+use crate::day_two::DayTwo;
+use crate::solution::{Answer, Solution, SolveError};
+
+#[derive(Clone, Copy)]
+pub struct Day {
+    pub day: u32,
+    pub solution: &'static dyn Solution,
+}
+
+impl Day {
+    pub fn solve(&self, part: u32, input: &str) -> Result<Answer, SolveError> {
+        match part {
+            1 => self.solution.part1(input),
+            2 => self.solution.part2(input),
+            _ => Err(SolveError::Unsolved),
+        }
+    }
+}
+
+/// Every ported day, in order
+pub fn days() -> Vec<Day> {
+    let entries: [(u32, &'static dyn Solution); 16] = [
+        (1, &DayOne),
+        (2, &DayTwo),
+        (3, &DayThree),
+        (4, &DayFour),
+        (5, &DayFive),
+        (6, &DaySix),
+        (7, &DaySeven),
+        (8, &DayEight),
+        (9, &DayNine),
+        (11, &DayEleven),
+        (12, &DayTwelve),
+        (13, &DayThirteen),
+        (15, &DayFifteen),
+        (17, &DaySeventeen),
+        (19, &DayNineteen),
+        (20, &DayTwenty),
+    ];
+    entries.iter().map(|(day, solution)| Day { day: *day, solution: *solution }).collect()
+}
+
+pub fn find(day: u32) -> Option<Day> {
+    days().into_iter().find(|entry| entry.day == day)
+}